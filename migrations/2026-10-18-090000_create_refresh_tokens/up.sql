@@ -0,0 +1,13 @@
+CREATE TABLE refresh_tokens (
+       id SERIAL PRIMARY KEY,
+       "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+       -- tokens obtained by rotating each other share the family
+       family TEXT NOT NULL,
+       token_hash TEXT NOT NULL UNIQUE,
+       created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+       expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+       used_at TIMESTAMP WITH TIME ZONE,
+       revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX refresh_tokens_family_idx ON refresh_tokens (family);
@@ -0,0 +1,8 @@
+CREATE TABLE password_resets (
+       id SERIAL PRIMARY KEY,
+       "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+       token_hash TEXT NOT NULL UNIQUE,
+       created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+       expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+       used_at TIMESTAMP WITH TIME ZONE
+);
@@ -0,0 +1,31 @@
+CREATE TABLE tags (
+  id SERIAL PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE article_tags (
+  article INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+  tag INTEGER NOT NULL REFERENCES tags ON DELETE CASCADE,
+  PRIMARY KEY (article, tag)
+);
+
+CREATE INDEX article_tags_tag_idx ON article_tags (tag);
+
+-- Normalize existing tags the same way the application does: trimmed,
+-- lowercased, at most 32 characters, without blanks and duplicates.
+UPDATE articles SET tag_list = ARRAY(
+  SELECT name FROM (
+    SELECT left(lower(btrim(t)), 32) AS name, min(i) AS position
+    FROM unnest(tag_list) WITH ORDINALITY AS u(t, i)
+    WHERE btrim(t) <> ''
+    GROUP BY 1
+  ) normalized
+  ORDER BY position
+);
+
+INSERT INTO tags (name)
+SELECT DISTINCT unnest(tag_list) FROM articles;
+
+INSERT INTO article_tags (article, tag)
+SELECT articles.id, tags.id
+FROM articles JOIN tags ON tags.name = ANY(articles.tag_list);
@@ -0,0 +1,5 @@
+CREATE TABLE tag_follows (
+  "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+  tag INTEGER NOT NULL REFERENCES tags ON DELETE CASCADE,
+  PRIMARY KEY ("user", tag)
+);
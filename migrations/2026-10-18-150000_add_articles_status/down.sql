@@ -0,0 +1 @@
+ALTER TABLE articles DROP COLUMN status, DROP COLUMN published_at;
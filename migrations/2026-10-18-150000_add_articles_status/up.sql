@@ -0,0 +1,8 @@
+ALTER TABLE articles
+  ADD COLUMN status TEXT NOT NULL DEFAULT 'published'
+    CHECK (status IN ('draft', 'published', 'scheduled')),
+  ADD COLUMN published_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE articles SET published_at = created_at;
+
+CREATE INDEX articles_scheduled_idx ON articles (published_at) WHERE status = 'scheduled';
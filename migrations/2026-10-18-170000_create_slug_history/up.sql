@@ -0,0 +1,5 @@
+CREATE TABLE slug_history (
+  slug TEXT PRIMARY KEY,
+  article INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
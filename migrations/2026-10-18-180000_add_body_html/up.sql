@@ -0,0 +1,4 @@
+-- Rendered markdown, filled in by the application on write. Rows written
+-- before it existed stay NULL and are rendered on read.
+ALTER TABLE articles ADD COLUMN body_html TEXT;
+ALTER TABLE comments ADD COLUMN body_html TEXT;
@@ -0,0 +1 @@
+ALTER TABLE articles DROP COLUMN word_count, DROP COLUMN reading_time_minutes, DROP COLUMN excerpt;
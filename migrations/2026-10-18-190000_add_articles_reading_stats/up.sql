@@ -0,0 +1,12 @@
+ALTER TABLE articles
+  ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0,
+  ADD COLUMN reading_time_minutes INTEGER NOT NULL DEFAULT 0,
+  ADD COLUMN excerpt TEXT NOT NULL DEFAULT '';
+
+-- Approximation of what the application computes from rendered markdown:
+-- words of the raw body, and the description as excerpt since it was
+-- required so far.
+UPDATE articles SET
+  word_count = coalesce(cardinality(regexp_split_to_array(nullif(btrim(body), ''), '\s+')), 0),
+  excerpt = description;
+UPDATE articles SET reading_time_minutes = ceil(word_count / 200.0);
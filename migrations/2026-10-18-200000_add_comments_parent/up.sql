@@ -0,0 +1,5 @@
+ALTER TABLE comments
+  ADD COLUMN parent INTEGER REFERENCES comments ON DELETE CASCADE,
+  ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX comments_parent_idx ON comments (parent);
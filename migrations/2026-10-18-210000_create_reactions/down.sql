@@ -0,0 +1,10 @@
+CREATE TABLE favorites (
+       "user" INTEGER REFERENCES users ON DELETE CASCADE,
+       article INTEGER REFERENCES articles ON DELETE CASCADE,
+       PRIMARY KEY ("user", article)
+);
+
+INSERT INTO favorites ("user", article)
+SELECT "user", article FROM reactions WHERE kind = 'like';
+
+DROP TABLE reactions;
@@ -0,0 +1,16 @@
+CREATE TABLE reactions (
+  "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+  article INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+  kind TEXT NOT NULL
+    CHECK (kind IN ('like', 'clap', 'insightful', 'heart', 'laugh')),
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  PRIMARY KEY ("user", article, kind)
+);
+
+CREATE INDEX reactions_article_idx ON reactions (article, kind);
+
+-- Favorites are likes from now on, `favorites_count` counts them.
+INSERT INTO reactions ("user", article, kind)
+SELECT "user", article, 'like' FROM favorites;
+
+DROP TABLE favorites;
@@ -0,0 +1,11 @@
+ALTER TABLE articles ADD COLUMN tag_list TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE articles SET tag_list = ARRAY(
+  SELECT tags.name
+  FROM article_tags JOIN tags ON tags.id = article_tags.tag
+  WHERE article_tags.article = articles.id
+  ORDER BY article_tags.position
+);
+
+ALTER TABLE articles ALTER COLUMN tag_list DROP DEFAULT;
+ALTER TABLE article_tags DROP COLUMN position;
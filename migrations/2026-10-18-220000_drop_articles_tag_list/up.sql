@@ -0,0 +1,8 @@
+-- Tags live in `article_tags` alone, `position` keeps the order they were given in.
+ALTER TABLE article_tags ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+UPDATE article_tags SET position = array_position(articles.tag_list, tags.name)
+FROM articles, tags
+WHERE articles.id = article_tags.article AND tags.id = article_tags.tag;
+
+ALTER TABLE articles DROP COLUMN tag_list;
@@ -1,4 +1,6 @@
 use crate::config::AppState;
+use crate::database::{self, Db};
+use crate::errors::AppError;
 use jwt::{DecodingKey, EncodingKey};
 use rocket::http::Status;
 use rocket::outcome::Outcome;
@@ -16,6 +18,8 @@ pub struct Auth {
     /// user id
     pub id: i32,
     pub username: String,
+    /// `token_version` of the user at the moment of issuing
+    pub version: i32,
 }
 
 impl Auth {
@@ -31,26 +35,41 @@ impl<'r> FromRequest<'r> for Auth {
 
     /// Extract Auth token from the "Authorization" header.
     ///
-    /// Handlers with Auth guard will fail with 503 error.
+    /// Handlers with Auth guard will fail with 403 error if there is no token,
+    /// and with 401 error if the token is invalid, expired or revoked.
     /// Handlers with Option<Auth> will be called with None.
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Auth, Self::Error> {
+        let token = match req
+            .headers()
+            .get_one("authorization")
+            .and_then(extract_token_from_header)
+        {
+            Some(token) => token,
+            None => return Outcome::Failure((Status::Forbidden, ())),
+        };
+
         let state = req.rocket().state::<AppState>().unwrap();
-        if let Some(auth) = extract_auth_from_request(req, &state.secret) {
-            Outcome::Success(auth)
-        } else {
-            Outcome::Failure((Status::Forbidden, ()))
+        let auth = match decode_token(token, &state.secret) {
+            Some(auth) => auth,
+            None => return Outcome::Failure((Status::Unauthorized, ())),
+        };
+
+        let db = match req.guard::<Db>().await {
+            Outcome::Success(db) => db,
+            _ => return Outcome::Failure((Status::ServiceUnavailable, ())),
+        };
+        let id = auth.id;
+        let version = db
+            .run(move |conn| database::users::token_version(conn, id))
+            .await;
+        match version {
+            Ok(version) if version == auth.version => Outcome::Success(auth),
+            Ok(_) | Err(AppError::NotFound) => Outcome::Failure((Status::Unauthorized, ())),
+            Err(_) => Outcome::Failure((Status::InternalServerError, ())),
         }
     }
 }
 
-fn extract_auth_from_request(request: &Request, secret: &[u8]) -> Option<Auth> {
-    request
-        .headers()
-        .get_one("authorization")
-        .and_then(extract_token_from_header)
-        .and_then(|token| decode_token(token, secret))
-}
-
 fn extract_token_from_header(header: &str) -> Option<&str> {
     if header.starts_with(config::TOKEN_PREFIX) {
         Some(&header[config::TOKEN_PREFIX.len()..])
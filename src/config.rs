@@ -1,3 +1,5 @@
+use crate::database::slugs::SlugStrategy;
+use chrono::Duration;
 use rocket::config::Config;
 use rocket::fairing::AdHoc;
 use rocket::figment::Figment;
@@ -12,8 +14,21 @@ pub const DATE_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%.3fZ";
 
 pub const TOKEN_PREFIX: &'static str = "Token ";
 
+/// Default JWT lifetime in seconds, overridden by `ACCESS_TOKEN_LIFETIME`.
+const ACCESS_TOKEN_LIFETIME: i64 = 60 * 60;
+
+/// Default refresh token lifetime in seconds, overridden by `REFRESH_TOKEN_LIFETIME`.
+const REFRESH_TOKEN_LIFETIME: i64 = 30 * 24 * 60 * 60;
+
+/// Default interval in seconds between publishing due scheduled articles,
+/// overridden by `PUBLISH_INTERVAL`.
+pub const PUBLISH_INTERVAL: i64 = 30;
+
 pub struct AppState {
     pub secret: Vec<u8>,
+    pub access_token_lifetime: Duration,
+    pub refresh_token_lifetime: Duration,
+    pub slug_strategy: SlugStrategy,
 }
 
 impl AppState {
@@ -30,11 +45,44 @@ impl AppState {
 
             rocket.manage(AppState {
                 secret: secret.into_bytes(),
+                access_token_lifetime: lifetime_from_env(
+                    "ACCESS_TOKEN_LIFETIME",
+                    ACCESS_TOKEN_LIFETIME,
+                ),
+                refresh_token_lifetime: lifetime_from_env(
+                    "REFRESH_TOKEN_LIFETIME",
+                    REFRESH_TOKEN_LIFETIME,
+                ),
+                slug_strategy: slug_strategy_from_env(),
             })
         })
     }
 }
 
+/// Read lifetime in seconds from environment variable `name`.
+pub fn lifetime_from_env(name: &str, default: i64) -> Duration {
+    let seconds = env::var(name)
+        .map(|value| {
+            value.parse::<i64>().unwrap_or_else(|_| {
+                panic!("{} environment variable should parse to an integer", name)
+            })
+        })
+        .unwrap_or(default);
+    Duration::seconds(seconds)
+}
+
+/// Read `SLUG_STRATEGY` (`random` or `counter`), defaulting by the
+/// `random-suffix` feature.
+fn slug_strategy_from_env() -> SlugStrategy {
+    env::var("SLUG_STRATEGY")
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|err| panic!("SLUG_STRATEGY environment variable: {}", err))
+        })
+        .unwrap_or_default()
+}
+
 /// Create rocket config from environment variables
 pub fn from_env() -> Figment {
     let port = env::var("PORT")
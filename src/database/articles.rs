@@ -1,19 +1,35 @@
-use crate::database::OffsetLimit;
-use crate::models::article::{Article, ArticleJson};
+use crate::database::slugs::{slugify, SlugStrategy};
+use crate::database::{self, OffsetLimit};
+use crate::errors::{AppError, Errors, FieldName};
+use crate::markdown;
+use crate::models::article::{Article, ArticleJson, ArticleStatus, SearchResultJson};
+use crate::models::reaction::{ReactionKind, Reactions};
+use crate::models::revision::Revision;
 use crate::models::user::User;
+use crate::schema::article_tags;
 use crate::schema::articles;
-use crate::schema::favorites;
 use crate::schema::follows;
+use crate::schema::reactions;
+use crate::schema::tag_follows;
+use crate::schema::tags;
 use crate::schema::users;
+use chrono::{DateTime, SecondsFormat, Utc};
 use diesel;
+use diesel::dsl::{sql, InnerJoin, IntoBoxed};
+use diesel::pg::Pg;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
-use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use diesel::sql_types::{Bool, Float, Nullable, Text, Timestamptz};
 use serde::Deserialize;
-use slug;
 
-const SUFFIX_LEN: usize = 6;
 const DEFAULT_LIMIT: i64 = 20;
+const EXCERPT_LEN: usize = 200;
+const WORDS_PER_MINUTE: i32 = 200;
+const HEADLINE_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MaxFragments=2";
+/// `body` with html escaped, `ts_headline` leaves markup in its input as is,
+/// so that only the `<mark>` tags it adds remain.
+const ESCAPED_BODY: &str = "replace(replace(replace(replace(articles.body, \
+     '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), '\"', '&quot;')";
 
 #[derive(Insertable)]
 #[table_name = "articles"]
@@ -23,281 +39,889 @@ struct NewArticle<'a> {
     body: &'a str,
     slug: &'a str,
     author: i32,
-    tag_list: &'a Vec<String>,
+    status: ArticleStatus,
+    published_at: Option<DateTime<Utc>>,
+    body_html: &'a str,
+    word_count: i32,
+    reading_time_minutes: i32,
+    excerpt: &'a str,
 }
 
-pub fn create(
-    conn: &PgConnection,
-    author: i32,
-    title: &str,
-    description: &str,
-    body: &str,
-    tag_list: &Vec<String>,
-) -> ArticleJson {
-    let new_article = &NewArticle {
-        title,
-        description,
-        body,
-        author,
-        tag_list,
-        slug: &slugify(title),
-    };
+/// Stored alongside the body, so listings don't have to parse markdown.
+struct ReadingStats {
+    word_count: i32,
+    reading_time_minutes: i32,
+    excerpt: String,
+}
 
-    let author = users::table
-        .find(author)
-        .get_result::<User>(conn)
-        .expect("Error loading author");
+impl ReadingStats {
+    fn of(description: &str, body: &str) -> Self {
+        let text = markdown::plain_text(body);
+        let word_count = text.split_whitespace().count() as i32;
+        let excerpt = if description.trim().is_empty() {
+            markdown::excerpt(&text, EXCERPT_LEN)
+        } else {
+            description.to_string()
+        };
+        ReadingStats {
+            word_count,
+            reading_time_minutes: (word_count + WORDS_PER_MINUTE - 1) / WORDS_PER_MINUTE,
+            excerpt,
+        }
+    }
+}
 
-    diesel::insert_into(articles::table)
-        .values(new_article)
-        .get_result::<Article>(conn)
-        .expect("Error creating article")
-        .attach(author, false)
+/// Requested publication of a new article. Without either field it's
+/// published right away.
+#[derive(Deserialize, Default)]
+pub struct Publication {
+    pub status: Option<ArticleStatus>,
+    /// Time to publish at, implies `scheduled` status
+    #[serde(rename = "publishAt")]
+    pub publish_at: Option<DateTime<Utc>>,
 }
 
-fn slugify(title: &str) -> String {
-    if cfg!(feature = "random-suffix") {
-        format!("{}-{}", slug::slugify(title), generate_suffix(SUFFIX_LEN))
-    } else {
-        slug::slugify(title)
+impl Publication {
+    fn resolve(&self) -> Result<(ArticleStatus, Option<DateTime<Utc>>), Errors> {
+        let status = match (self.status, self.publish_at) {
+            (Some(status), _) => status,
+            (None, Some(_)) => ArticleStatus::Scheduled,
+            (None, None) => ArticleStatus::Published,
+        };
+        match status {
+            ArticleStatus::Draft => Ok((status, None)),
+            ArticleStatus::Published => Ok((status, Some(Utc::now()))),
+            ArticleStatus::Scheduled => match self.publish_at {
+                Some(publish_at) if publish_at > Utc::now() => Ok((status, Some(publish_at))),
+                Some(_) => Err(Errors::new(&[("publishAt", "must be in the future")])),
+                None => Err(Errors::new(&[("publishAt", "can't be blank")])),
+            },
+        }
     }
 }
 
-fn generate_suffix(len: usize) -> String {
-    let mut rng = thread_rng();
-    (0..len)
-        .map(|_| rng.sample(Alphanumeric))
-        .map(char::from)
-        .collect()
+#[allow(clippy::too_many_arguments)]
+pub fn create(
+    conn: &PgConnection,
+    author: i32,
+    title: &str,
+    description: &str,
+    body: &str,
+    tag_list: &[String],
+    publication: &Publication,
+    slug_strategy: SlugStrategy,
+) -> Result<ArticleJson, AppError> {
+    let (status, published_at) = publication.resolve()?;
+    let tag_list = &database::tags::normalize(tag_list);
+    let body_html = &markdown::render(body);
+    let stats = &ReadingStats::of(description, body);
+    let author = users::table.find(author).get_result::<User>(conn)?;
+
+    conn.transaction(|| {
+        let article = database::slugs::with_unique(conn, title, slug_strategy, |slug| {
+            let new_article = &NewArticle {
+                title,
+                description,
+                body,
+                author: author.id,
+                slug,
+                status,
+                published_at,
+                body_html,
+                word_count: stats.word_count,
+                reading_time_minutes: stats.reading_time_minutes,
+                excerpt: &stats.excerpt,
+            };
+            Ok(diesel::insert_into(articles::table)
+                .values(new_article)
+                .get_result::<Article>(conn)?)
+        })?;
+        database::slugs::release(conn, &article.slug)?;
+        database::tags::set(conn, article.id, tag_list)?;
+        Ok(article.attach(
+            author.to_profile(false),
+            tag_list.clone(),
+            Reactions::default(),
+        ))
+    })
 }
 
 #[derive(FromForm, Default)]
 pub struct FindArticles {
-    pub tag: Option<String>,
+    /// may be repeated, see `tag_mode`
+    pub tag: Vec<String>,
+    #[field(name = "tagMode")]
+    pub tag_mode: Option<TagMode>,
     pub author: Option<String>,
     /// favorited by user
     pub favorited: Option<String>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// `nextCursor` of the previous page, takes precedence over `offset`
+    pub cursor: Option<String>,
+    /// `prevCursor` of the next page, takes precedence over `offset`
+    pub before: Option<String>,
+    pub sort: Option<Sort>,
+    /// include rendered `bodyHtml`
+    pub html: bool,
+}
+
+#[derive(FromForm, Default)]
+pub struct FeedArticles {
+    pub source: Option<FeedSource>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub cursor: Option<String>,
+    pub before: Option<String>,
+    pub sort: Option<Sort>,
+    /// include rendered `bodyHtml`
+    pub html: bool,
+}
+
+#[derive(FromForm, Default)]
+pub struct SearchArticles {
+    /// search terms in `websearch_to_tsquery` syntax, e.g. `rust -"borrow checker"`
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// include rendered `bodyHtml`
+    pub html: bool,
+}
+
+/// What the feed is made of.
+#[derive(FromFormField, Clone, Copy, PartialEq, Default)]
+pub enum FeedSource {
+    /// Articles by followed authors
+    #[default]
+    Authors,
+    /// Articles carrying followed tags
+    Tags,
+    /// Both of the above
+    All,
+}
+
+/// How multiple `tag` params are combined.
+#[derive(FromFormField, Clone, Copy, PartialEq, Default)]
+pub enum TagMode {
+    /// Article has at least one of the tags
+    #[default]
+    Any,
+    /// Article has every tag
+    All,
+}
+
+/// Order of article listings, descending with ties broken by id.
+#[derive(FromFormField, Clone, Copy, PartialEq, Default)]
+pub enum Sort {
+    /// Most recently published first, unpublished articles by their creation
+    #[default]
+    Created,
+    /// Recently updated first
+    Updated,
+    /// Most favorited first
+    Favorites,
+}
+
+/// A page of articles.
+#[derive(Default)]
+pub struct ArticlesPage {
+    pub articles: Vec<ArticleJson>,
+    /// Number of matching articles, on every page
+    pub count: i64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+/// Position of an article in the listing, handed out to clients as an opaque string.
+///
+/// Holds the value of the sort column along with the article id.
+enum Cursor {
+    Published(DateTime<Utc>, i32),
+    Updated(DateTime<Utc>, i32),
+    Favorites(i32, i32),
+}
+
+impl Cursor {
+    fn of(article: &Article, sort: Sort) -> Self {
+        match sort {
+            Sort::Created => Cursor::Published(
+                article.published_at.unwrap_or(article.created_at),
+                article.id,
+            ),
+            Sort::Updated => Cursor::Updated(article.updated_at, article.id),
+            Sort::Favorites => Cursor::Favorites(article.favorites_count, article.id),
+        }
+    }
+
+    fn encode(&self) -> String {
+        let time = |time: &DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Micros, true);
+        let raw = match self {
+            Cursor::Published(published_at, id) => {
+                format!("published|{}|{}", time(published_at), id)
+            }
+            Cursor::Updated(updated_at, id) => format!("updated|{}|{}", time(updated_at), id),
+            Cursor::Favorites(count, id) => format!("favorites|{}|{}", count, id),
+        };
+        base64::encode_config(raw, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Decode cursor, which must have been made for the same `sort`.
+    fn decode(field: FieldName, value: &str, sort: Sort) -> Result<Self, Errors> {
+        let invalid = || Errors::new(&[(field, "is invalid")]);
+        let time = |time: &str| {
+            DateTime::parse_from_rfc3339(time)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|_| invalid())
+        };
+
+        let raw = base64::decode_config(value, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let mut parts = raw.splitn(3, '|');
+        let (kind, value, id) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(kind), Some(value), Some(id)) => (kind, value, id),
+            _ => return Err(invalid()),
+        };
+        let id = id.parse().map_err(|_| invalid())?;
+
+        match (kind, sort) {
+            ("published", Sort::Created) => Ok(Cursor::Published(time(value)?, id)),
+            ("updated", Sort::Updated) => Ok(Cursor::Updated(time(value)?, id)),
+            ("favorites", Sort::Favorites) => {
+                Ok(Cursor::Favorites(value.parse().map_err(|_| invalid())?, id))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+sql_function! {
+    /// First of the arguments which isn't null.
+    fn coalesce(x: Nullable<Timestamptz>, y: Timestamptz) -> Timestamptz;
+}
+
+/// Time the article was published at, or created at while it isn't.
+fn published_at() -> coalesce::HelperType<articles::published_at, articles::created_at> {
+    coalesce(articles::published_at, articles::created_at)
+}
+
+/// Continue `$query` from the cursor position, comparing `$column` with `$value`
+/// and the article id with `$id` by `$cmp`, ordering both by `$order`.
+macro_rules! keyset {
+    ($query:expr, $column:expr, $value:expr, $id:expr, $cmp:ident, $order:ident) => {
+        $query
+            .filter(
+                $column
+                    .$cmp($value)
+                    .or($column.eq($value).and(articles::id.$cmp($id))),
+            )
+            .order(($column.$order(), articles::id.$order()))
+    };
 }
 
 pub fn find(
     conn: &PgConnection,
     params: &FindArticles,
     user_id: Option<i32>,
-) -> (Vec<ArticleJson>, i64) {
-    let mut query = articles::table
+) -> Result<ArticlesPage, AppError> {
+    load(conn, params, None, user_id)
+}
+
+/// Articles of the authors or tags followed by the user, depending on `source`.
+pub fn feed(
+    conn: &PgConnection,
+    params: &FeedArticles,
+    user_id: i32,
+) -> Result<ArticlesPage, AppError> {
+    let source = params.source.unwrap_or_default();
+    let params = FindArticles {
+        limit: params.limit,
+        offset: params.offset,
+        cursor: params.cursor.clone(),
+        before: params.before.clone(),
+        sort: params.sort,
+        ..Default::default()
+    };
+    load(conn, &params, Some((user_id, source)), Some(user_id))
+}
+
+/// Articles matching the search terms, most relevant first.
+///
+/// `search_vector` is a generated column, so it isn't part of the diesel schema
+/// and is only referenced from raw sql here.
+pub fn search(
+    conn: &PgConnection,
+    params: &SearchArticles,
+    user_id: Option<i32>,
+) -> Result<(Vec<SearchResultJson>, i64), AppError> {
+    let q = params.q.as_deref().map(str::trim).unwrap_or_default();
+    if q.is_empty() {
+        return Err(Errors::new(&[("q", "can't be blank")]).into());
+    }
+
+    let snippet = sql::<Text>(&format!(
+        "ts_headline('english', {}, websearch_to_tsquery('english', ",
+        ESCAPED_BODY
+    ))
+    .bind::<Text, _>(q.to_string())
+    .sql(&format!("), '{}')", HEADLINE_OPTIONS));
+    let matches = sql::<Bool>("articles.search_vector @@ websearch_to_tsquery('english', ")
+        .bind::<Text, _>(q.to_string())
+        .sql(")");
+    let rank = sql::<Float>("ts_rank(articles.search_vector, websearch_to_tsquery('english', ")
+        .bind::<Text, _>(q.to_string())
+        .sql("))");
+
+    let (rows, count) = articles::table
         .inner_join(users::table)
-        .left_join(
-            favorites::table.on(articles::id
-                .eq(favorites::article)
-                .and(favorites::user.eq(user_id.unwrap_or(0)))), // TODO: refactor
+        .select((articles::all_columns, users::all_columns, snippet))
+        .filter(matches)
+        .filter(articles::status.eq(ArticleStatus::Published))
+        .order((rank.desc(), articles::id.desc()))
+        .offset_and_limit(
+            params.offset.unwrap_or(0),
+            params.limit.unwrap_or(DEFAULT_LIMIT),
         )
-        .select((
-            articles::all_columns,
-            users::all_columns,
-            favorites::user.nullable().is_not_null(),
-        ))
-        .into_boxed();
+        .load_and_count::<(Article, User, String)>(conn)?;
+
+    let ids: Vec<i32> = rows.iter().map(|(article, ..)| article.id).collect();
+    let mut tags = database::tags::load(conn, &ids)?;
+    let mut reactions = database::reactions::load(conn, user_id, &ids)?;
+    let following = database::profiles::following_ids(
+        conn,
+        user_id,
+        rows.iter().map(|(article, ..)| article.author),
+    )?;
+    let results = rows
+        .into_iter()
+        .map(|(article, author, snippet)| {
+            let tag_list = tags.remove(&article.id).unwrap_or_default();
+            let reactions = reactions.remove(&article.id).unwrap_or_default();
+            let following = following.contains(&author.id);
+            SearchResultJson {
+                article: article.attach(author.to_profile(following), tag_list, reactions),
+                snippet,
+            }
+        })
+        .collect();
+    Ok((results, count))
+}
+
+/// Load a page of articles matching `params`, optionally only those followed
+/// by `follower` through `FeedSource`.
+fn load(
+    conn: &PgConnection,
+    params: &FindArticles,
+    follower: Option<(i32, FeedSource)>,
+    user_id: Option<i32>,
+) -> Result<ArticlesPage, AppError> {
+    let sort = params.sort.unwrap_or_default();
+    let after = match params.cursor {
+        Some(ref cursor) => Some(Cursor::decode("cursor", cursor, sort)?),
+        None => None,
+    };
+    let before = match params.before {
+        Some(ref before) => Some(Cursor::decode("before", before, sort)?),
+        None => None,
+    };
+
+    let mut query = match matching(conn, params, follower, user_id)? {
+        Some(query) => query.select((articles::all_columns, users::all_columns)),
+        None => return Ok(ArticlesPage::default()),
+    };
+
+    // Keyset pagination: walk from the cursor, towards the end of the listing
+    // or towards its start for `before`, which is reversed back afterwards.
+    query = match (&before, &after) {
+        (Some(cursor), _) => match *cursor {
+            Cursor::Published(value, id) => keyset!(query, published_at(), value, id, gt, asc),
+            Cursor::Updated(value, id) => keyset!(query, articles::updated_at, value, id, gt, asc),
+            Cursor::Favorites(value, id) => {
+                keyset!(query, articles::favorites_count, value, id, gt, asc)
+            }
+        },
+        (None, Some(cursor)) => match *cursor {
+            Cursor::Published(value, id) => keyset!(query, published_at(), value, id, lt, desc),
+            Cursor::Updated(value, id) => keyset!(query, articles::updated_at, value, id, lt, desc),
+            Cursor::Favorites(value, id) => {
+                keyset!(query, articles::favorites_count, value, id, lt, desc)
+            }
+        },
+        (None, None) => match sort {
+            Sort::Created => query.order((published_at().desc(), articles::id.desc())),
+            Sort::Updated => query.order((articles::updated_at.desc(), articles::id.desc())),
+            Sort::Favorites => query.order((articles::favorites_count.desc(), articles::id.desc())),
+        },
+    };
+
+    let keyset = after.is_some() || before.is_some();
+    let offset = if keyset {
+        0
+    } else {
+        params.offset.unwrap_or(0)
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    let (mut rows, count) = query
+        .offset_and_limit(offset, limit)
+        .load_and_count::<(Article, User)>(conn)?;
+    if before.is_some() {
+        rows.reverse();
+    }
+    // The page query only sees articles past the cursor, count them all.
+    let count = if keyset {
+        match matching(conn, params, follower, user_id)? {
+            Some(query) => query.count().get_result(conn)?,
+            None => 0,
+        }
+    } else {
+        count
+    };
+
+    let full = rows.len() as i64 == limit;
+    let first = rows
+        .first()
+        .map(|(article, _)| Cursor::of(article, sort).encode());
+    let last = rows
+        .last()
+        .map(|(article, _)| Cursor::of(article, sort).encode());
+    let (next_cursor, prev_cursor) = if before.is_some() {
+        (last, if full { first } else { None })
+    } else {
+        (
+            if full { last } else { None },
+            if after.is_some() || offset > 0 {
+                first
+            } else {
+                None
+            },
+        )
+    };
+
+    let ids: Vec<i32> = rows.iter().map(|(article, _)| article.id).collect();
+    let mut tags = database::tags::load(conn, &ids)?;
+    let mut reactions = database::reactions::load(conn, user_id, &ids)?;
+    let following = database::profiles::following_ids(
+        conn,
+        user_id,
+        rows.iter().map(|(article, _)| article.author),
+    )?;
+
+    let articles = rows
+        .into_iter()
+        .map(|(article, author)| {
+            let tag_list = tags.remove(&article.id).unwrap_or_default();
+            let reactions = reactions.remove(&article.id).unwrap_or_default();
+            let following = following.contains(&author.id);
+            article.attach(author.to_profile(following), tag_list, reactions)
+        })
+        .collect();
+    Ok(ArticlesPage {
+        articles,
+        count,
+        next_cursor,
+        prev_cursor,
+    })
+}
+
+/// Boxed query of articles joined with their authors.
+type Matching = IntoBoxed<'static, InnerJoin<articles::table, users::table>, Pg>;
+
+/// Articles matching the filters of `params` in no particular order, `None`
+/// when nothing can match.
+fn matching(
+    conn: &PgConnection,
+    params: &FindArticles,
+    follower: Option<(i32, FeedSource)>,
+    user_id: Option<i32>,
+) -> Result<Option<Matching>, AppError> {
+    let mut query = articles::table.inner_join(users::table).into_boxed();
+    if let Some((follower, source)) = follower {
+        let by_authors = articles::author.eq_any(
+            follows::table
+                .select(follows::followed)
+                .filter(follows::follower.eq(follower)),
+        );
+        let by_tags = articles::id.eq_any(
+            article_tags::table.select(article_tags::article).filter(
+                article_tags::tag.eq_any(
+                    tag_follows::table
+                        .select(tag_follows::tag)
+                        .filter(tag_follows::user.eq(follower)),
+                ),
+            ),
+        );
+        query = match source {
+            FeedSource::Authors => query.filter(by_authors),
+            FeedSource::Tags => query.filter(by_tags),
+            FeedSource::All => query.filter(by_authors.or(by_tags)),
+        }
+    }
+    // Viewers see their own drafts in listings, but never in the feed.
+    let published = articles::status.eq(ArticleStatus::Published);
+    query = match (follower, user_id) {
+        (None, Some(viewer)) => query.filter(published.or(articles::author.eq(viewer))),
+        _ => query.filter(published),
+    };
     if let Some(ref author) = params.author {
-        query = query.filter(users::username.eq(author))
+        query = query.filter(users::username.eq(author.clone()))
     }
-    if let Some(ref tag) = params.tag {
-        query = query.or_filter(articles::tag_list.contains(vec![tag]))
+    let names = database::tags::normalize(&params.tag);
+    let tagged = |names: Vec<String>| {
+        articles::id.eq_any(
+            article_tags::table
+                .inner_join(tags::table)
+                .select(article_tags::article)
+                .filter(tags::name.eq_any(names)),
+        )
+    };
+    if !names.is_empty() {
+        match params.tag_mode.unwrap_or_default() {
+            TagMode::Any => query = query.filter(tagged(names)),
+            TagMode::All => {
+                for name in names {
+                    query = query.filter(tagged(vec![name]));
+                }
+            }
+        }
     }
     if let Some(ref favorited) = params.favorited {
         let result = users::table
             .select(users::id)
             .filter(users::username.eq(favorited))
-            .get_result::<i32>(conn);
+            .get_result::<i32>(conn)
+            .optional()?;
         match result {
-            Ok(id) => {
-                query = query.filter(diesel::dsl::sql(&format!(
-                    "articles.id IN (SELECT favorites.article FROM favorites WHERE favorites.user = {})",
-                    id
-                )));
+            Some(id) => {
+                query = query.filter(
+                    articles::id.eq_any(
+                        reactions::table
+                            .select(reactions::article)
+                            .filter(reactions::user.eq(id))
+                            .filter(reactions::kind.eq(ReactionKind::Like)),
+                    ),
+                );
             }
-            Err(err) => match err {
-                diesel::result::Error::NotFound => return (vec![], 0),
-                _ => panic!("Cannot load favorited user: {}", err),
-            },
+            None => return Ok(None),
         }
     }
+    Ok(Some(query))
+}
 
-    query
-        .offset_and_limit(
-            params.offset.unwrap_or(0),
-            params.limit.unwrap_or(DEFAULT_LIMIT),
-        )
-        .load_and_count::<(Article, User, bool)>(conn)
-        .map(|(res, count)| {
-            (
-                res.into_iter()
-                    .map(|(article, author, favorited)| article.attach(author, favorited))
-                    .collect(),
-                count,
-            )
-        })
-        .expect("Cannot load articles")
+pub fn find_one(
+    conn: &PgConnection,
+    slug: &str,
+    user_id: Option<i32>,
+) -> Result<ArticleJson, AppError> {
+    let article = find_visible(conn, slug, user_id)?;
+    populate(conn, article, user_id)
 }
 
-pub fn find_one(conn: &PgConnection, slug: &str, user_id: Option<i32>) -> Option<ArticleJson> {
-    let article = articles::table
-        .filter(articles::slug.eq(slug))
-        .first::<Article>(conn)
-        .map_err(|err| eprintln!("articles::find_one: {}", err))
-        .ok()?;
+/// Favoriting is liking.
+pub fn favorite(conn: &PgConnection, slug: &str, user_id: i32) -> Result<ArticleJson, AppError> {
+    react(conn, slug, user_id, ReactionKind::Like)
+}
 
-    let favorited = user_id
-        .map(|id| is_favorite(conn, &article, id))
-        .unwrap_or(false);
+pub fn unfavorite(conn: &PgConnection, slug: &str, user_id: i32) -> Result<ArticleJson, AppError> {
+    unreact(conn, slug, user_id, ReactionKind::Like)
+}
 
-    Some(populate(conn, article, favorited))
+/// React to the article, reacting the same way twice changes nothing.
+pub fn react(
+    conn: &PgConnection,
+    slug: &str,
+    user_id: i32,
+    kind: ReactionKind,
+) -> Result<ArticleJson, AppError> {
+    conn.transaction(|| {
+        let article_id = visible_id(conn, slug, user_id)?;
+        database::reactions::add(conn, user_id, article_id, kind)?;
+
+        let article = articles::table
+            .find(article_id)
+            .get_result::<Article>(conn)?;
+        populate(conn, article, Some(user_id))
+    })
 }
 
-#[derive(FromForm, Default)]
-pub struct FeedArticles {
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
+/// Take back a reaction to the article, if there is one.
+pub fn unreact(
+    conn: &PgConnection,
+    slug: &str,
+    user_id: i32,
+    kind: ReactionKind,
+) -> Result<ArticleJson, AppError> {
+    conn.transaction(|| {
+        let article_id = visible_id(conn, slug, user_id)?;
+        database::reactions::remove(conn, user_id, article_id, kind)?;
+
+        let article = articles::table
+            .find(article_id)
+            .get_result::<Article>(conn)?;
+        populate(conn, article, Some(user_id))
+    })
+}
+
+/// Article with the slug, unless it's unpublished and not by the user.
+/// Old slugs of renamed articles are resolved too, as they are wherever an
+/// article is looked up by slug.
+pub fn find_visible(
+    conn: &PgConnection,
+    slug: &str,
+    user_id: Option<i32>,
+) -> Result<Article, AppError> {
+    let slug = database::slugs::canonical(conn, slug)?;
+    let article = articles::table
+        .filter(articles::slug.eq(slug))
+        .first::<Article>(conn)?;
+    if article.status != ArticleStatus::Published && Some(article.author) != user_id {
+        return Err(AppError::NotFound);
+    }
+    Ok(article)
 }
 
-// select * from articles where author in (select followed from follows where follower = 7);
-pub fn feed(conn: &PgConnection, params: &FeedArticles, user_id: i32) -> Vec<ArticleJson> {
+/// Id of the article with the slug, unless it's unpublished and not by the user.
+fn visible_id(conn: &PgConnection, slug: &str, user_id: i32) -> QueryResult<i32> {
+    let slug = database::slugs::canonical(conn, slug)?;
     articles::table
+        .select(articles::id)
+        .filter(articles::slug.eq(slug))
         .filter(
-            articles::author.eq_any(
-                follows::table
-                    .select(follows::followed)
-                    .filter(follows::follower.eq(user_id)),
-            ),
-        )
-        .inner_join(users::table)
-        .left_join(
-            favorites::table.on(articles::id
-                .eq(favorites::article)
-                .and(favorites::user.eq(user_id))),
+            articles::status
+                .eq(ArticleStatus::Published)
+                .or(articles::author.eq(user_id)),
         )
-        .select((
-            articles::all_columns,
-            users::all_columns,
-            favorites::user.nullable().is_not_null(),
-        ))
-        .limit(params.limit.unwrap_or(DEFAULT_LIMIT))
-        .offset(params.offset.unwrap_or(0))
-        .load::<(Article, User, bool)>(conn)
-        .expect("Cannot load feed")
-        .into_iter()
-        .map(|(article, author, favorited)| article.attach(author, favorited))
-        .collect()
+        .get_result(conn)
 }
 
-pub fn favorite(conn: &PgConnection, slug: &str, user_id: i32) -> Option<ArticleJson> {
-    conn.transaction::<_, diesel::result::Error, _>(|| {
-        let article = diesel::update(articles::table.filter(articles::slug.eq(slug)))
-            .set(articles::favorites_count.eq(articles::favorites_count + 1))
-            .get_result::<Article>(conn)?;
+/// Publish a draft or scheduled article right away.
+pub fn publish(conn: &PgConnection, slug: &str, user_id: i32) -> Result<ArticleJson, AppError> {
+    let article = find_visible(conn, slug, Some(user_id))?;
+    if article.author != user_id {
+        return Err(AppError::Forbidden);
+    }
 
-        diesel::insert_into(favorites::table)
-            .values((
-                favorites::user.eq(user_id),
-                favorites::article.eq(article.id),
+    let article = if article.status == ArticleStatus::Published {
+        article
+    } else {
+        diesel::update(articles::table.find(article.id))
+            .set((
+                articles::status.eq(ArticleStatus::Published),
+                articles::published_at.eq(Utc::now()),
+                articles::updated_at.eq(Utc::now()),
             ))
-            .execute(conn)?;
+            .get_result(conn)?
+    };
 
-        Ok(populate(conn, article, true))
-    })
-    .map_err(|err| eprintln!("articles::favorite: {}", err))
-    .ok()
+    populate(conn, article, Some(user_id))
 }
 
-pub fn unfavorite(conn: &PgConnection, slug: &str, user_id: i32) -> Option<ArticleJson> {
-    conn.transaction::<_, diesel::result::Error, _>(|| {
-        let article = diesel::update(articles::table.filter(articles::slug.eq(slug)))
-            .set(articles::favorites_count.eq(articles::favorites_count - 1))
-            .get_result::<Article>(conn)?;
-
-        diesel::delete(favorites::table.find((user_id, article.id))).execute(conn)?;
+/// Publish scheduled articles which are due, returns how many were published.
+pub fn publish_scheduled(conn: &PgConnection) -> QueryResult<usize> {
+    diesel::update(
+        articles::table
+            .filter(articles::status.eq(ArticleStatus::Scheduled))
+            .filter(articles::published_at.le(Utc::now())),
+    )
+    .set((
+        articles::status.eq(ArticleStatus::Published),
+        articles::updated_at.eq(Utc::now()),
+    ))
+    .execute(conn)
+}
 
-        Ok(populate(conn, article, false))
-    })
-    .map_err(|err| eprintln!("articles::unfavorite: {}", err))
-    .ok()
+#[derive(Deserialize, Default, Clone, Validate)]
+pub struct UpdateArticleData {
+    #[validate(length(min = 1))]
+    title: Option<String>,
+    description: Option<String>,
+    #[validate(length(min = 1))]
+    body: Option<String>,
+    #[serde(rename = "tagList")]
+    #[validate(custom = "database::tags::validate")]
+    tag_list: Option<Vec<String>>,
 }
 
-#[derive(Deserialize, AsChangeset, Default, Clone)]
+/// Columns set by `update`, tags are kept in `article_tags` instead.
+#[derive(AsChangeset)]
 #[table_name = "articles"]
-pub struct UpdateArticleData {
+struct ArticleChanges {
     title: Option<String>,
     description: Option<String>,
     body: Option<String>,
-    #[serde(skip)]
     slug: Option<String>,
-    #[serde(rename = "tagList")]
-    tag_list: Vec<String>,
+    body_html: Option<String>,
+    word_count: Option<i32>,
+    reading_time_minutes: Option<i32>,
+    excerpt: Option<String>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<Revision> for UpdateArticleData {
+    fn from(revision: Revision) -> Self {
+        UpdateArticleData {
+            title: Some(revision.title),
+            description: Some(revision.description),
+            body: Some(revision.body),
+            tag_list: Some(revision.tag_list),
+        }
+    }
+}
+
+impl UpdateArticleData {
+    /// Diesel refuses to run an update without any columns to set.
+    fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.body.is_none()
+            && self.tag_list.is_none()
+    }
 }
 
 pub fn update(
     conn: &PgConnection,
     slug: &str,
     user_id: i32,
-    mut data: UpdateArticleData,
-) -> Option<ArticleJson> {
-    if let Some(ref title) = data.title {
-        data.slug = Some(slugify(&title));
-    }
-    // TODO: check for not_found
-    let article = diesel::update(articles::table.filter(articles::slug.eq(slug)))
-        .set(&data)
-        .get_result(conn)
-        .expect("Error loading article");
+    data: UpdateArticleData,
+    slug_strategy: SlugStrategy,
+) -> Result<ArticleJson, AppError> {
+    let article = conn.transaction::<_, AppError, _>(|| {
+        let article = find_visible(conn, slug, Some(user_id))?;
+        let article = articles::table
+            .find(article.id)
+            .for_update()
+            .first::<Article>(conn)?;
+
+        if article.author != user_id {
+            return Err(AppError::Forbidden);
+        }
+        if data.is_empty() {
+            return Ok(article);
+        }
+        database::revisions::record(conn, &article)?;
 
-    let favorited = is_favorite(conn, &article, user_id);
-    Some(populate(conn, article, favorited))
-}
+        if let Some(ref tag_list) = data.tag_list {
+            database::tags::set(conn, article.id, &database::tags::normalize(tag_list))?;
+        }
+        let mut changes = ArticleChanges {
+            title: data.title.clone(),
+            description: data.description.clone(),
+            body: data.body.clone(),
+            slug: None,
+            body_html: data.body.as_deref().map(markdown::render),
+            word_count: None,
+            reading_time_minutes: None,
+            excerpt: None,
+            updated_at: Utc::now(),
+        };
+        if data.body.is_some() || data.description.is_some() {
+            let stats = ReadingStats::of(
+                data.description.as_ref().unwrap_or(&article.description),
+                data.body.as_ref().unwrap_or(&article.body),
+            );
+            changes.word_count = Some(stats.word_count);
+            changes.reading_time_minutes = Some(stats.reading_time_minutes);
+            changes.excerpt = Some(stats.excerpt);
+        }
 
-pub fn delete(conn: &PgConnection, slug: &str, user_id: i32) {
-    let result = diesel::delete(
-        articles::table.filter(articles::slug.eq(slug).and(articles::author.eq(user_id))),
-    )
-    .execute(conn);
-    if let Err(err) = result {
-        eprintln!("articles::delete: {}", err);
-    }
+        // The slug is kept unless the title change shows up in it.
+        let renamed = match data.title {
+            Some(ref title) => slugify(title) != slugify(&article.title),
+            None => false,
+        };
+        if !renamed {
+            return Ok(diesel::update(articles::table.find(article.id))
+                .set(&changes)
+                .get_result(conn)?);
+        }
+
+        let title = data.title.clone().unwrap_or_default();
+        let updated = database::slugs::with_unique(conn, &title, slug_strategy, |slug| {
+            changes.slug = Some(slug.to_string());
+            Ok(diesel::update(articles::table.find(article.id))
+                .set(&changes)
+                .get_result::<Article>(conn)?)
+        })?;
+        database::slugs::record(conn, article.id, &article.slug)?;
+        database::slugs::release(conn, &updated.slug)?;
+        Ok(updated)
+    })?;
+
+    populate(conn, article, Some(user_id))
 }
 
-fn is_favorite(conn: &PgConnection, article: &Article, user_id: i32) -> bool {
-    use diesel::dsl::exists;
-    use diesel::select;
+pub fn delete(conn: &PgConnection, slug: &str, user_id: i32) -> Result<(), AppError> {
+    let article = find_visible(conn, slug, Some(user_id))?;
+    if article.author != user_id {
+        return Err(AppError::Forbidden);
+    }
 
-    select(exists(favorites::table.find((user_id, article.id))))
-        .get_result(conn)
-        .expect("Error loading favorited")
+    diesel::delete(articles::table.find(article.id)).execute(conn)?;
+    Ok(())
 }
 
-fn populate(conn: &PgConnection, article: Article, favorited: bool) -> ArticleJson {
-    let author = users::table
-        .find(article.author)
-        .get_result::<User>(conn)
-        .expect("Error loading author");
+/// Attach the author and reactions, as seen by the user, if any.
+fn populate(
+    conn: &PgConnection,
+    article: Article,
+    user_id: Option<i32>,
+) -> Result<ArticleJson, AppError> {
+    let author = users::table.find(article.author).get_result::<User>(conn)?;
+    let following = match user_id {
+        Some(id) => database::profiles::is_following(conn, &author, id)?,
+        None => false,
+    };
 
-    article.attach(author, favorited)
-}
+    let tag_list = database::tags::of(conn, article.id)?;
+    let reactions = database::reactions::load(conn, user_id, &[article.id])?
+        .remove(&article.id)
+        .unwrap_or_default();
 
-pub fn tags(conn: &PgConnection) -> Vec<String> {
-    articles::table
-        .select(diesel::dsl::sql("distinct unnest(tag_list)"))
-        .load::<String>(conn)
-        .expect("Cannot load tags")
+    Ok(article.attach(author.to_profile(following), tag_list, reactions))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Duration;
 
     #[test]
-    fn test_generate_suffix() {
-        for len in 3..9 {
-            assert_eq!(generate_suffix(len).len(), len);
-        }
+    /// Test that the background publisher publishes due articles.
+    fn test_publish_scheduled() {
+        let conn = database::test_connection();
+        conn.test_transaction::<_, AppError, _>(|| {
+            let author = diesel::insert_into(users::table)
+                .values((
+                    users::username.eq("scheduler"),
+                    users::email.eq("scheduler@realworld.io"),
+                    users::hash.eq(""),
+                ))
+                .get_result::<User>(&conn)?;
+            let publication = Publication {
+                status: None,
+                publish_at: Some(Utc::now() + Duration::hours(1)),
+            };
+            let slug = create(
+                &conn,
+                author.id,
+                "Due article",
+                "Published by the scheduler",
+                "Body",
+                &[],
+                &publication,
+                SlugStrategy::Counter,
+            )?
+            .slug;
+            assert!(find_visible(&conn, &slug, None).is_err());
+
+            let due = Utc::now() - Duration::minutes(1);
+            let before = diesel::update(articles::table.filter(articles::slug.eq(&slug)))
+                .set((articles::published_at.eq(due), articles::updated_at.eq(due)))
+                .get_result::<Article>(&conn)?;
+            assert!(publish_scheduled(&conn)? >= 1);
+
+            let article = find_visible(&conn, &slug, None)?;
+            assert_eq!(article.status, ArticleStatus::Published);
+            assert!(article.updated_at > before.updated_at);
+            Ok(())
+        });
     }
 }
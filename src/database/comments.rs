@@ -1,11 +1,18 @@
+use crate::database;
+use crate::database::OffsetLimit;
+use crate::errors::{AppError, Errors};
+use crate::markdown;
 use crate::models::comment::{Comment, CommentJson};
 use crate::models::user::User;
-use crate::schema::articles;
 use crate::schema::comments;
 use crate::schema::users;
+use chrono::Utc;
 use diesel;
+use diesel::dsl::exists;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
+use diesel::select;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Insertable)]
 #[table_name = "comments"]
@@ -13,65 +20,252 @@ struct NewComment<'a> {
     body: &'a str,
     author: i32,
     article: i32,
+    body_html: &'a str,
+    parent: Option<i32>,
 }
 
-pub fn create(conn: &PgConnection, author: i32, slug: &str, body: &str) -> CommentJson {
-    let article_id = articles::table
-        .select(articles::id)
-        .filter(articles::slug.eq(slug))
-        .get_result::<i32>(conn)
-        .expect("Cannot find article id");
+/// Body of deleted comments which are kept for their replies.
+const TOMBSTONE: &str = "[deleted]";
+
+/// Comment on the article, or a reply to the `parent` comment of it.
+///
+/// Like everything here, only articles visible to the user can be commented,
+/// see `articles::find_visible`.
+pub fn create(
+    conn: &PgConnection,
+    author: i32,
+    slug: &str,
+    body: &str,
+    parent: Option<i32>,
+) -> Result<CommentJson, AppError> {
+    let article_id = database::articles::find_visible(conn, slug, Some(author))?.id;
+
+    if let Some(parent) = parent {
+        let parent_exists = select(exists(
+            comments::table
+                .filter(comments::id.eq(parent))
+                .filter(comments::article.eq(article_id))
+                .filter(comments::deleted_at.is_null()),
+        ))
+        .get_result::<bool>(conn)?;
+        if !parent_exists {
+            return Err(Errors::new(&[("parentId", "is invalid")]).into());
+        }
+    }
+
     let new_comment = &NewComment {
         body,
         author,
         article: article_id,
+        body_html: &markdown::render(body),
+        parent,
     };
 
-    let author = users::table
-        .find(author)
-        .get_result::<User>(conn)
-        .expect("Error loading author");
+    let author = users::table.find(author).get_result::<User>(conn)?;
 
-    diesel::insert_into(comments::table)
+    Ok(diesel::insert_into(comments::table)
         .values(new_comment)
-        .get_result::<Comment>(conn)
-        .expect("Error creating comment")
-        .attach(author)
+        .get_result::<Comment>(conn)?
+        .attach(author.to_profile(false)))
+}
+
+const DEFAULT_LIMIT: i64 = 20;
+
+#[derive(FromForm, Default)]
+pub struct FindComments {
+    /// number of top level comments, replies come along with their thread
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// order of top level comments, replies are always oldest first
+    pub order: Option<CommentOrder>,
+    /// include rendered `bodyHtml`
+    pub html: bool,
 }
 
-pub fn find_by_slug(conn: &PgConnection, slug: &str) -> Vec<CommentJson> {
-    let result = comments::table
-        .inner_join(articles::table)
+/// Order of top level comments by creation, ties broken by id.
+#[derive(FromFormField, Clone, Copy, PartialEq, Default)]
+pub enum CommentOrder {
+    /// Oldest first
+    #[default]
+    Asc,
+    /// Newest first
+    Desc,
+}
+
+/// Page of comment threads of the article with the given slug, together with
+/// the number of top level comments. Authors are followed as seen by the user.
+pub fn find_by_slug(
+    conn: &PgConnection,
+    slug: &str,
+    params: &FindComments,
+    user_id: Option<i32>,
+) -> Result<(Vec<CommentJson>, i64), AppError> {
+    let article_id = database::articles::find_visible(conn, slug, user_id)?.id;
+
+    let query = comments::table
         .inner_join(users::table)
         .select((comments::all_columns, users::all_columns))
-        .filter(articles::slug.eq(slug))
-        .get_results::<(Comment, User)>(conn)
-        .expect("Cannot load comments");
+        .filter(comments::article.eq(article_id))
+        .filter(comments::parent.is_null())
+        .into_boxed();
+    let query = match params.order.unwrap_or_default() {
+        CommentOrder::Asc => query.order((comments::created_at, comments::id)),
+        CommentOrder::Desc => query.order((comments::created_at.desc(), comments::id.desc())),
+    };
+    let (mut rows, count) = query
+        .offset_and_limit(
+            params.offset.unwrap_or(0),
+            params.limit.unwrap_or(DEFAULT_LIMIT),
+        )
+        .load_and_count::<(Comment, User)>(conn)?;
 
-    result
+    // Load the replies of the page level by level.
+    let mut parents: Vec<i32> = rows.iter().map(|(comment, _)| comment.id).collect();
+    while !parents.is_empty() {
+        let replies = comments::table
+            .inner_join(users::table)
+            .select((comments::all_columns, users::all_columns))
+            .filter(comments::parent.eq_any(&parents))
+            .order((comments::created_at, comments::id))
+            .load::<(Comment, User)>(conn)?;
+        parents = replies.iter().map(|(comment, _)| comment.id).collect();
+        rows.extend(replies);
+    }
+
+    let following = database::profiles::following_ids(
+        conn,
+        user_id,
+        rows.iter().map(|(comment, _)| comment.author),
+    )?;
+    let comments = rows
         .into_iter()
-        .map(|(comment, author)| comment.attach(author))
-        .collect()
+        .map(|(comment, author)| {
+            let following = following.contains(&author.id);
+            comment.attach(author.to_profile(following))
+        })
+        .collect();
+    Ok((thread(comments), count))
 }
 
-pub fn delete(conn: &PgConnection, author: i32, slug: &str, comment_id: i32) {
-    use diesel::dsl::exists;
-    use diesel::select;
-
-    let belongs_to_author_result = select(exists(
-        articles::table.filter(articles::slug.eq(slug).and(articles::author.eq(author))),
-    ))
-    .get_result::<bool>(conn);
+/// Order comments depth first: replies follow their parent, siblings are
+/// kept in the given order. Sets `depth` on the way.
+fn thread(comments: Vec<CommentJson>) -> Vec<CommentJson> {
+    let ids: HashSet<i32> = comments.iter().map(|comment| comment.id).collect();
+    let mut replies: HashMap<Option<i32>, Vec<CommentJson>> = HashMap::new();
+    for comment in comments {
+        let parent = comment.parent_id.filter(|parent| ids.contains(parent));
+        replies.entry(parent).or_default().push(comment);
+    }
 
-    if let Err(err) = belongs_to_author_result {
-        match err {
-            diesel::result::Error::NotFound => return,
-            _ => panic!("Cannot find article by author: {}", err),
+    let mut threaded = Vec::with_capacity(ids.len());
+    let mut stack: Vec<(CommentJson, i32)> = replies
+        .remove(&None)
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .map(|comment| (comment, 0))
+        .collect();
+    while let Some((mut comment, depth)) = stack.pop() {
+        comment.depth = depth;
+        if let Some(children) = replies.remove(&Some(comment.id)) {
+            stack.extend(children.into_iter().rev().map(|reply| (reply, depth + 1)));
         }
+        threaded.push(comment);
     }
+    threaded
+}
 
-    let result = diesel::delete(comments::table.filter(comments::id.eq(comment_id))).execute(conn);
-    if let Err(err) = result {
-        eprintln!("comments::delete: {}", err);
+/// Change the body of a comment of the article with the given slug.
+///
+/// Only the comment author is allowed to do so, deleted comments can't be edited.
+pub fn update(
+    conn: &PgConnection,
+    user_id: i32,
+    slug: &str,
+    comment_id: i32,
+    body: &str,
+) -> Result<CommentJson, AppError> {
+    let article = database::articles::find_visible(conn, slug, Some(user_id))?;
+    let comment_author = comments::table
+        .select(comments::author)
+        .filter(comments::article.eq(article.id))
+        .filter(comments::id.eq(comment_id))
+        .filter(comments::deleted_at.is_null())
+        .get_result::<i32>(conn)?;
+
+    if user_id != comment_author {
+        return Err(AppError::Forbidden);
     }
+
+    let comment = diesel::update(comments::table.find(comment_id))
+        .set((
+            comments::body.eq(body),
+            comments::body_html.eq(markdown::render(body)),
+            comments::updated_at.eq(Utc::now()),
+        ))
+        .get_result::<Comment>(conn)?;
+    let author = users::table.find(comment_author).get_result::<User>(conn)?;
+
+    Ok(comment.attach(author.to_profile(false)))
+}
+
+/// Delete a comment of the article with the given slug.
+///
+/// Only the comment author or the article author is allowed to do so.
+/// Comments with replies are replaced by a tombstone to keep the thread,
+/// tombstones go away along with their last reply.
+pub fn delete(
+    conn: &PgConnection,
+    user_id: i32,
+    slug: &str,
+    comment_id: i32,
+) -> Result<(), AppError> {
+    let article = database::articles::find_visible(conn, slug, Some(user_id))?;
+    let (comment_author, parent) = comments::table
+        .select((comments::author, comments::parent))
+        .filter(comments::article.eq(article.id))
+        .filter(comments::id.eq(comment_id))
+        .get_result::<(i32, Option<i32>)>(conn)?;
+
+    if user_id != comment_author && user_id != article.author {
+        return Err(AppError::Forbidden);
+    }
+
+    conn.transaction(|| {
+        if has_replies(conn, comment_id)? {
+            diesel::update(comments::table.find(comment_id))
+                .set((
+                    comments::body.eq(TOMBSTONE),
+                    comments::body_html.eq(None::<String>),
+                    comments::deleted_at.eq(Utc::now()),
+                ))
+                .execute(conn)?;
+            return Ok(());
+        }
+
+        diesel::delete(comments::table.find(comment_id)).execute(conn)?;
+        let mut parent = parent;
+        while let Some(id) = parent {
+            if has_replies(conn, id)? {
+                break;
+            }
+            parent = diesel::delete(
+                comments::table
+                    .find(id)
+                    .filter(comments::deleted_at.is_not_null()),
+            )
+            .returning(comments::parent)
+            .get_result::<Option<i32>>(conn)
+            .optional()?
+            .flatten();
+        }
+        Ok(())
+    })
+}
+
+fn has_replies(conn: &PgConnection, comment_id: i32) -> QueryResult<bool> {
+    select(exists(
+        comments::table.filter(comments::parent.eq(comment_id)),
+    ))
+    .get_result(conn)
 }
@@ -1,11 +1,29 @@
 pub mod articles;
 pub mod comments;
+pub mod password_resets;
 pub mod profiles;
+pub mod reactions;
+pub mod refresh_tokens;
+pub mod revisions;
+pub mod slugs;
+pub mod tags;
+mod tokens;
 pub mod users;
 
 #[database("diesel_postgres_pool")]
 pub struct Db(diesel::PgConnection);
 
+/// Connection for tests which need the database, best used with
+/// `Connection::test_transaction` so that nothing is left behind.
+#[cfg(test)]
+pub fn test_connection() -> diesel::PgConnection {
+    use diesel::Connection;
+
+    dotenv::dotenv().ok();
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    diesel::PgConnection::establish(&url).expect("database must be available")
+}
+
 use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::query_builder::*;
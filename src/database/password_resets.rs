@@ -0,0 +1,73 @@
+use crate::database;
+use crate::database::tokens::{generate, hash};
+use crate::errors::{AppError, Errors};
+use crate::schema::password_resets;
+use crate::schema::users;
+use chrono::{DateTime, Duration, Utc};
+use diesel;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+const TOKEN_LEN: usize = 32;
+const TOKEN_LIFETIME_HOURS: i64 = 1;
+
+#[derive(Insertable)]
+#[table_name = "password_resets"]
+struct NewPasswordReset<'a> {
+    user: i32,
+    token_hash: &'a str,
+    expires_at: DateTime<Utc>,
+}
+
+/// Create a reset token for the user with the given email.
+///
+/// Returns `None` if there is no such user. Callers shouldn't reveal that
+/// to the client.
+pub fn create(conn: &PgConnection, email: &str) -> Result<Option<String>, AppError> {
+    let user_id = users::table
+        .select(users::id)
+        .filter(users::email.eq(email))
+        .get_result::<i32>(conn)
+        .optional()?;
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Ok(None),
+    };
+
+    let token = generate(TOKEN_LEN);
+    diesel::insert_into(password_resets::table)
+        .values(&NewPasswordReset {
+            user: user_id,
+            token_hash: &hash(&token),
+            expires_at: Utc::now() + Duration::hours(TOKEN_LIFETIME_HOURS),
+        })
+        .execute(conn)?;
+    Ok(Some(token))
+}
+
+/// Set a new password using a reset token.
+///
+/// Every pending token of the user is spent, so each one works only once.
+pub fn confirm(conn: &PgConnection, token: &str, password: &str) -> Result<(), AppError> {
+    conn.transaction(|| {
+        let user_id = password_resets::table
+            .select(password_resets::user)
+            .filter(password_resets::token_hash.eq(hash(token)))
+            .filter(password_resets::used_at.is_null())
+            .filter(password_resets::expires_at.gt(Utc::now()))
+            .for_update()
+            .get_result::<i32>(conn)
+            .optional()?
+            .ok_or_else(|| Errors::new(&[("token", "is invalid")]))?;
+
+        diesel::update(
+            password_resets::table
+                .filter(password_resets::user.eq(user_id))
+                .filter(password_resets::used_at.is_null()),
+        )
+        .set(password_resets::used_at.eq(Utc::now()))
+        .execute(conn)?;
+
+        database::users::set_password(conn, user_id, password)
+    })
+}
@@ -1,3 +1,4 @@
+use crate::errors::AppError;
 use crate::schema::{follows, users};
 use diesel;
 use diesel::pg::PgConnection;
@@ -5,55 +6,72 @@ use diesel::prelude::*;
 
 use crate::models::user::{Profile, User};
 
-pub fn find(conn: &PgConnection, name: &str, user_id: Option<i32>) -> Option<Profile> {
+pub fn find(conn: &PgConnection, name: &str, user_id: Option<i32>) -> Result<Profile, AppError> {
     let user = users::table
         .filter(users::username.eq(name))
-        .get_result(conn)
-        .map_err(|err| eprintln!("find_user_by_name: {}", err))
-        .ok()?;
+        .get_result(conn)?;
 
-    let following = user_id
-        .map(|id| is_following(conn, &user, id))
-        .unwrap_or(false);
+    let following = match user_id {
+        Some(id) => is_following(conn, &user, id)?,
+        None => false,
+    };
 
-    Some(user.to_profile(following))
+    Ok(user.to_profile(following))
 }
 
-fn is_following(conn: &PgConnection, user: &User, user_id: i32) -> bool {
+pub fn is_following(conn: &PgConnection, user: &User, user_id: i32) -> QueryResult<bool> {
     use diesel::dsl::exists;
     use diesel::select;
 
-    select(exists(follows::table.find((user_id, user.id))))
-        .get_result(conn)
-        .expect("Error loading following")
+    select(exists(follows::table.find((user_id, user.id)))).get_result(conn)
 }
 
-pub fn follow(conn: &PgConnection, followed_name: &str, follower_id: i32) -> Option<Profile> {
+/// Ids of the given users followed by the user, if any.
+pub fn following_ids(
+    conn: &PgConnection,
+    user_id: Option<i32>,
+    ids: impl Iterator<Item = i32>,
+) -> QueryResult<Vec<i32>> {
+    match user_id {
+        Some(user_id) => follows::table
+            .select(follows::followed)
+            .filter(follows::follower.eq(user_id))
+            .filter(follows::followed.eq_any(ids.collect::<Vec<_>>()))
+            .load::<i32>(conn),
+        None => Ok(vec![]),
+    }
+}
+
+pub fn follow(
+    conn: &PgConnection,
+    followed_name: &str,
+    follower_id: i32,
+) -> Result<Profile, AppError> {
     let followed = users::table
         .filter(users::username.eq(followed_name))
-        .get_result::<User>(conn)
-        .expect("Cannot load followed");
+        .get_result::<User>(conn)?;
 
     diesel::insert_into(follows::table)
         .values((
             follows::followed.eq(followed.id),
             follows::follower.eq(follower_id),
         ))
-        .execute(conn)
-        .expect("Cannot follow");
+        .on_conflict_do_nothing()
+        .execute(conn)?;
 
-    Some(followed.to_profile(true))
+    Ok(followed.to_profile(true))
 }
 
-pub fn unfollow(conn: &PgConnection, followed_name: &str, follower_id: i32) -> Option<Profile> {
+pub fn unfollow(
+    conn: &PgConnection,
+    followed_name: &str,
+    follower_id: i32,
+) -> Result<Profile, AppError> {
     let followed = users::table
         .filter(users::username.eq(followed_name))
-        .get_result::<User>(conn)
-        .expect("Cannot load followed");
+        .get_result::<User>(conn)?;
 
-    diesel::delete(follows::table.find((follower_id, followed.id)))
-        .execute(conn)
-        .expect("Cannot unfollow");
+    diesel::delete(follows::table.find((follower_id, followed.id))).execute(conn)?;
 
-    Some(followed.to_profile(false))
+    Ok(followed.to_profile(false))
 }
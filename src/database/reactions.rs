@@ -0,0 +1,120 @@
+use crate::models::reaction::{ReactionKind, Reactions};
+use crate::schema::{articles, reactions};
+use diesel;
+use diesel::dsl::sql;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use std::collections::HashMap;
+
+/// Reactions to the given articles, `mine` being those of the user, if any.
+/// Articles without any reactions are left out.
+pub fn load(
+    conn: &PgConnection,
+    user_id: Option<i32>,
+    ids: &[i32],
+) -> QueryResult<HashMap<i32, Reactions>> {
+    let counts = reactions::table
+        .select((
+            reactions::article,
+            reactions::kind,
+            sql::<BigInt>("count(*)"),
+        ))
+        .filter(reactions::article.eq_any(ids))
+        .group_by((reactions::article, reactions::kind))
+        .load::<(i32, ReactionKind, i64)>(conn)?;
+    let mine = match user_id {
+        Some(user_id) => reactions::table
+            .select((reactions::article, reactions::kind))
+            .filter(reactions::user.eq(user_id))
+            .filter(reactions::article.eq_any(ids))
+            .load::<(i32, ReactionKind)>(conn)?,
+        None => vec![],
+    };
+
+    let mut result: HashMap<i32, Reactions> = HashMap::new();
+    for (article, kind, count) in counts {
+        result
+            .entry(article)
+            .or_default()
+            .counts
+            .insert(kind, count);
+    }
+    for (article, kind) in mine {
+        result.entry(article).or_default().mine.insert(kind);
+    }
+    Ok(result)
+}
+
+/// Add a reaction of the user, returns whether it wasn't there already.
+/// Likes are counted in `favorites_count` as well.
+pub fn add(
+    conn: &PgConnection,
+    user_id: i32,
+    article_id: i32,
+    kind: ReactionKind,
+) -> QueryResult<bool> {
+    let added = diesel::insert_into(reactions::table)
+        .values((
+            reactions::user.eq(user_id),
+            reactions::article.eq(article_id),
+            reactions::kind.eq(kind),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)?
+        > 0;
+    if added && kind == ReactionKind::Like {
+        diesel::update(articles::table.find(article_id))
+            .set(articles::favorites_count.eq(articles::favorites_count + 1))
+            .execute(conn)?;
+    }
+    Ok(added)
+}
+
+/// Remove a reaction of the user, returns whether it was there.
+pub fn remove(
+    conn: &PgConnection,
+    user_id: i32,
+    article_id: i32,
+    kind: ReactionKind,
+) -> QueryResult<bool> {
+    let removed =
+        diesel::delete(reactions::table.find((user_id, article_id, kind))).execute(conn)? > 0;
+    if removed && kind == ReactionKind::Like {
+        diesel::update(articles::table.find(article_id))
+            .set(articles::favorites_count.eq(articles::favorites_count - 1))
+            .execute(conn)?;
+    }
+    Ok(removed)
+}
+
+/// Set `favorites_count` of every article to its number of likes, returns how
+/// many articles were off.
+pub fn recount_favorites(conn: &PgConnection) -> QueryResult<usize> {
+    conn.transaction(|| {
+        // Locked first, so concurrent likes wait and count on top of the fix.
+        let counts = articles::table
+            .select((articles::id, articles::favorites_count))
+            .for_update()
+            .load::<(i32, i32)>(conn)?;
+        let likes = reactions::table
+            .select((reactions::article, sql::<BigInt>("count(*)")))
+            .filter(reactions::kind.eq(ReactionKind::Like))
+            .group_by(reactions::article)
+            .load::<(i32, i64)>(conn)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let mut fixed = 0;
+        for (id, count) in counts {
+            let actual = likes.get(&id).copied().unwrap_or(0) as i32;
+            if count != actual {
+                diesel::update(articles::table.find(id))
+                    .set(articles::favorites_count.eq(actual))
+                    .execute(conn)?;
+                fixed += 1;
+            }
+        }
+        Ok(fixed)
+    })
+}
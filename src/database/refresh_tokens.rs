@@ -0,0 +1,141 @@
+use crate::database::tokens::{generate, hash};
+use crate::errors::AppError;
+use crate::models::user::User;
+use crate::schema::refresh_tokens;
+use crate::schema::users;
+use chrono::{DateTime, Duration, Utc};
+use diesel;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+const TOKEN_LEN: usize = 48;
+const FAMILY_LEN: usize = 16;
+
+#[derive(Queryable)]
+struct RefreshToken {
+    id: i32,
+    user: i32,
+    family: String,
+    expires_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "refresh_tokens"]
+struct NewRefreshToken<'a> {
+    user: i32,
+    family: &'a str,
+    token_hash: &'a str,
+    expires_at: DateTime<Utc>,
+}
+
+/// Start a new token family for the user, e.g. on login.
+pub fn issue(conn: &PgConnection, user_id: i32, lifetime: Duration) -> Result<String, AppError> {
+    insert(conn, user_id, &generate(FAMILY_LEN), lifetime)
+}
+
+/// Exchange a refresh token for a new one of the same family.
+///
+/// Every token can be used only once. If a used token is presented again,
+/// it has probably leaked, so the whole family gets revoked.
+pub fn rotate(
+    conn: &PgConnection,
+    token: &str,
+    lifetime: Duration,
+) -> Result<(User, String), AppError> {
+    // Revocation must be committed even though the request is rejected,
+    // hence `None` instead of an error inside the transaction.
+    let rotated = conn.transaction::<_, AppError, _>(|| {
+        let found = refresh_tokens::table
+            .select((
+                refresh_tokens::id,
+                refresh_tokens::user,
+                refresh_tokens::family,
+                refresh_tokens::expires_at,
+                refresh_tokens::used_at,
+                refresh_tokens::revoked_at,
+            ))
+            .filter(refresh_tokens::token_hash.eq(hash(token)))
+            .for_update()
+            .first::<RefreshToken>(conn)
+            .optional()?;
+        let found = match found {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        if found.used_at.is_some() || found.revoked_at.is_some() {
+            eprintln!("refresh token reuse detected for user {}", found.user);
+            revoke_family(conn, &found.family)?;
+            return Ok(None);
+        }
+        if found.expires_at < Utc::now() {
+            return Ok(None);
+        }
+
+        diesel::update(refresh_tokens::table.find(found.id))
+            .set(refresh_tokens::used_at.eq(Utc::now()))
+            .execute(conn)?;
+
+        let user = users::table.find(found.user).get_result::<User>(conn)?;
+        let token = insert(conn, found.user, &found.family, lifetime)?;
+        Ok(Some((user, token)))
+    })?;
+
+    rotated.ok_or(AppError::Unauthorized)
+}
+
+/// Revoke the family of the given token. Unknown tokens are ignored.
+pub fn revoke(conn: &PgConnection, token: &str) -> Result<(), AppError> {
+    let family = refresh_tokens::table
+        .select(refresh_tokens::family)
+        .filter(refresh_tokens::token_hash.eq(hash(token)))
+        .get_result::<String>(conn)
+        .optional()?;
+
+    if let Some(family) = family {
+        revoke_family(conn, &family)?;
+    }
+    Ok(())
+}
+
+/// Revoke every token of the user, e.g. to log out everywhere.
+pub fn revoke_all(conn: &PgConnection, user_id: i32) -> Result<(), AppError> {
+    diesel::update(
+        refresh_tokens::table
+            .filter(refresh_tokens::user.eq(user_id))
+            .filter(refresh_tokens::revoked_at.is_null()),
+    )
+    .set(refresh_tokens::revoked_at.eq(Utc::now()))
+    .execute(conn)?;
+    Ok(())
+}
+
+fn revoke_family(conn: &PgConnection, family: &str) -> QueryResult<usize> {
+    diesel::update(
+        refresh_tokens::table
+            .filter(refresh_tokens::family.eq(family))
+            .filter(refresh_tokens::revoked_at.is_null()),
+    )
+    .set(refresh_tokens::revoked_at.eq(Utc::now()))
+    .execute(conn)
+}
+
+fn insert(
+    conn: &PgConnection,
+    user_id: i32,
+    family: &str,
+    lifetime: Duration,
+) -> Result<String, AppError> {
+    let token = generate(TOKEN_LEN);
+    diesel::insert_into(refresh_tokens::table)
+        .values(&NewRefreshToken {
+            user: user_id,
+            family,
+            token_hash: &hash(&token),
+            expires_at: Utc::now() + lifetime,
+        })
+        .execute(conn)?;
+    Ok(token)
+}
@@ -0,0 +1,169 @@
+use crate::database;
+use crate::database::slugs::SlugStrategy;
+use crate::errors::AppError;
+use crate::models::article::{Article, ArticleJson};
+use crate::models::revision::{DiffLine, Revision, RevisionDiff, RevisionJson};
+use crate::schema::article_revisions;
+use diesel;
+use diesel::expression::dsl::max;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+#[derive(Insertable)]
+#[table_name = "article_revisions"]
+struct NewRevision<'a> {
+    article: i32,
+    number: i32,
+    title: &'a str,
+    description: &'a str,
+    body: &'a str,
+    tag_list: &'a Vec<String>,
+}
+
+/// Columns of `Revision`.
+const COLUMNS: (
+    article_revisions::number,
+    article_revisions::title,
+    article_revisions::description,
+    article_revisions::body,
+    article_revisions::tag_list,
+    article_revisions::created_at,
+) = (
+    article_revisions::number,
+    article_revisions::title,
+    article_revisions::description,
+    article_revisions::body,
+    article_revisions::tag_list,
+    article_revisions::created_at,
+);
+
+/// Save the current version of the article before it gets updated.
+///
+/// Callers should hold a lock on the article row, so numbers don't collide.
+pub fn record(conn: &PgConnection, article: &Article) -> QueryResult<()> {
+    let last = article_revisions::table
+        .select(max(article_revisions::number))
+        .filter(article_revisions::article.eq(article.id))
+        .get_result::<Option<i32>>(conn)?;
+
+    let tag_list = database::tags::of(conn, article.id)?;
+    diesel::insert_into(article_revisions::table)
+        .values(&NewRevision {
+            article: article.id,
+            number: last.unwrap_or(0) + 1,
+            title: &article.title,
+            description: &article.description,
+            body: &article.body,
+            tag_list: &tag_list,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Revisions of the article, latest first.
+pub fn list(
+    conn: &PgConnection,
+    slug: &str,
+    user_id: Option<i32>,
+) -> Result<Vec<RevisionJson>, AppError> {
+    let article = database::articles::find_visible(conn, slug, user_id)?;
+    Ok(article_revisions::table
+        .select(COLUMNS)
+        .filter(article_revisions::article.eq(article.id))
+        .order(article_revisions::number.desc())
+        .load::<Revision>(conn)?
+        .into_iter()
+        .map(Revision::into_json)
+        .collect())
+}
+
+pub fn find(
+    conn: &PgConnection,
+    slug: &str,
+    number: i32,
+    user_id: Option<i32>,
+) -> Result<RevisionJson, AppError> {
+    let article = database::articles::find_visible(conn, slug, user_id)?;
+    Ok(load(conn, article.id, number)?.into_json())
+}
+
+/// Diff revision `from` against revision `to`, or against the current version.
+pub fn diff(
+    conn: &PgConnection,
+    slug: &str,
+    from: i32,
+    to: Option<i32>,
+    user_id: Option<i32>,
+) -> Result<RevisionDiff, AppError> {
+    let article = database::articles::find_visible(conn, slug, user_id)?;
+    let old = load(conn, article.id, from)?;
+    let (title, description, body) = match to {
+        Some(to) => {
+            let new = load(conn, article.id, to)?;
+            (new.title, new.description, new.body)
+        }
+        None => (article.title, article.description, article.body),
+    };
+
+    Ok(RevisionDiff {
+        from,
+        to,
+        title: diff_lines(&old.title, &title),
+        description: diff_lines(&old.description, &description),
+        body: diff_lines(&old.body, &body),
+    })
+}
+
+/// Make the revision current again. The replaced version is recorded as a
+/// new revision, so restoring can be undone.
+pub fn restore(
+    conn: &PgConnection,
+    slug: &str,
+    number: i32,
+    user_id: i32,
+    slug_strategy: SlugStrategy,
+) -> Result<ArticleJson, AppError> {
+    let article = database::articles::find_visible(conn, slug, Some(user_id))?;
+    if article.author != user_id {
+        return Err(AppError::Forbidden);
+    }
+    let revision = load(conn, article.id, number)?;
+    database::articles::update(conn, &article.slug, user_id, revision.into(), slug_strategy)
+}
+
+fn load(conn: &PgConnection, article_id: i32, number: i32) -> QueryResult<Revision> {
+    article_revisions::table
+        .select(COLUMNS)
+        .filter(article_revisions::article.eq(article_id))
+        .filter(article_revisions::number.eq(number))
+        .get_result(conn)
+}
+
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    diff::lines(old, new)
+        .into_iter()
+        .map(|line| match line {
+            diff::Result::Left(line) => DiffLine::Delete(line.to_string()),
+            diff::Result::Both(line, _) => DiffLine::Equal(line.to_string()),
+            diff::Result::Right(line) => DiffLine::Insert(line.to_string()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nc\nd"),
+            vec![
+                DiffLine::Equal("a".to_string()),
+                DiffLine::Delete("b".to_string()),
+                DiffLine::Equal("c".to_string()),
+                DiffLine::Insert("d".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::errors::AppError;
+use crate::schema::articles;
+use crate::schema::slug_history;
+use diesel;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use slug;
+use std::str::FromStr;
+
+const SUFFIX_LEN: usize = 6;
+/// Slug for titles without anything `slug::slugify` can transliterate.
+const FALLBACK_SLUG: &str = "article";
+/// Insert attempts before giving up on concurrent writers taking our slugs.
+const MAX_ATTEMPTS: usize = 5;
+/// Static segments under `/articles/`, an article with such a slug couldn't be
+/// reached.
+const RESERVED_SLUGS: &[&str] = &["feed", "search"];
+
+/// How article slugs are made unique, configured by `SLUG_STRATEGY`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlugStrategy {
+    /// Append random alphanumerics to every slug: `title-x1Y2z3`
+    Random,
+    /// Append a counter to repeated slugs only: `title`, `title-2`, `title-3`
+    Counter,
+}
+
+impl Default for SlugStrategy {
+    fn default() -> Self {
+        if cfg!(feature = "random-suffix") {
+            SlugStrategy::Random
+        } else {
+            SlugStrategy::Counter
+        }
+    }
+}
+
+impl FromStr for SlugStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(SlugStrategy::Random),
+            "counter" => Ok(SlugStrategy::Counter),
+            _ => Err(format!("unknown slug strategy: {}", s)),
+        }
+    }
+}
+
+/// Slug of the title without any suffix.
+pub fn slugify(title: &str) -> String {
+    let slug = slug::slugify(title);
+    if slug.is_empty() {
+        FALLBACK_SLUG.to_string()
+    } else {
+        slug
+    }
+}
+
+/// Run `write` with a unique slug for the title, e.g. to insert an article.
+///
+/// Every attempt runs in a savepoint, so a unique violation caused by a
+/// concurrent writer doesn't abort the outer transaction and is retried
+/// with the next candidate.
+pub fn with_unique<T, F>(
+    conn: &PgConnection,
+    title: &str,
+    strategy: SlugStrategy,
+    mut write: F,
+) -> Result<T, AppError>
+where
+    F: FnMut(&str) -> Result<T, AppError>,
+{
+    let base = slugify(title);
+    for _ in 0..MAX_ATTEMPTS {
+        let slug = match strategy {
+            SlugStrategy::Random => format!("{}-{}", base, generate_suffix(SUFFIX_LEN)),
+            SlugStrategy::Counter => next_free(conn, &base)?,
+        };
+        match conn.transaction(|| write(&slug)) {
+            Err(AppError::Conflict) => continue,
+            result => return result,
+        }
+    }
+    Err(AppError::Conflict)
+}
+
+/// `base` or the first `base-<n>` which isn't used by an article, now or before,
+/// or reserved for a route.
+fn next_free(conn: &PgConnection, base: &str) -> QueryResult<String> {
+    let pattern = format!("{}-%", base);
+    let mut taken = articles::table
+        .select(articles::slug)
+        .filter(articles::slug.eq(base).or(articles::slug.like(&pattern)))
+        .load::<String>(conn)?;
+    taken.extend(
+        slug_history::table
+            .select(slug_history::slug)
+            .filter(
+                slug_history::slug
+                    .eq(base)
+                    .or(slug_history::slug.like(&pattern)),
+            )
+            .load::<String>(conn)?,
+    );
+
+    if !RESERVED_SLUGS.contains(&base) && !taken.iter().any(|slug| slug == base) {
+        return Ok(base.to_string());
+    }
+    let slug = (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|slug| !taken.contains(slug))
+        .expect("there is always a free counter");
+    Ok(slug)
+}
+
+fn generate_suffix(len: usize) -> String {
+    let mut rng = thread_rng();
+    (0..len)
+        .map(|_| rng.sample(Alphanumeric))
+        .map(char::from)
+        .collect()
+}
+
+/// Remember the previous slug of a renamed article, so old links keep working.
+pub fn record(conn: &PgConnection, article_id: i32, old_slug: &str) -> QueryResult<()> {
+    diesel::insert_into(slug_history::table)
+        .values((
+            slug_history::slug.eq(old_slug),
+            slug_history::article.eq(article_id),
+        ))
+        .on_conflict(slug_history::slug)
+        .do_update()
+        .set(slug_history::article.eq(article_id))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Forget the slug once an article takes it, so it can't point elsewhere.
+pub fn release(conn: &PgConnection, slug: &str) -> QueryResult<()> {
+    diesel::delete(slug_history::table.find(slug)).execute(conn)?;
+    Ok(())
+}
+
+/// Current slug of the article which used to have `slug`, or `slug` itself.
+pub fn canonical(conn: &PgConnection, slug: &str) -> QueryResult<String> {
+    let current = slug_history::table
+        .inner_join(articles::table)
+        .select(articles::slug)
+        .filter(slug_history::slug.eq(slug))
+        .get_result::<String>(conn)
+        .optional()?;
+    Ok(current.unwrap_or_else(|| slug.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_suffix() {
+        for len in 3..9 {
+            assert_eq!(generate_suffix(len).len(), len);
+        }
+    }
+
+    #[test]
+    /// Needs the database for the savepoints every attempt runs in.
+    fn test_with_unique_random_retries() {
+        let conn = crate::database::test_connection();
+
+        let mut tried = vec![];
+        let slug = conn.test_transaction::<_, AppError, _>(|| {
+            with_unique(&conn, "Random Slugs", SlugStrategy::Random, |slug| {
+                tried.push(slug.to_string());
+                if tried.len() < 3 {
+                    Err(AppError::Conflict)
+                } else {
+                    Ok(slug.to_string())
+                }
+            })
+        });
+
+        assert_eq!(tried.len(), 3);
+        assert_eq!(tried.last(), Some(&slug));
+        for slug in &tried {
+            let suffix = slug
+                .strip_prefix("random-slugs-")
+                .expect("must keep the title");
+            assert_eq!(suffix.len(), SUFFIX_LEN);
+        }
+        assert_ne!(tried[0], tried[1]);
+    }
+
+    #[test]
+    fn test_slugify_fallback() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("?!"), FALLBACK_SLUG);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::errors::{AppError, Errors};
+use crate::models::article::ArticleStatus;
+use crate::schema::article_tags;
+use crate::schema::articles;
+use crate::schema::tag_follows;
+use crate::schema::tags;
+use diesel;
+use diesel::dsl::sql;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use serde::Serialize;
+use std::collections::HashMap;
+use validator::ValidationError;
+
+/// Longest tag accepted, in characters, after trimming.
+pub const TAG_MAX_LEN: usize = 32;
+
+#[derive(Queryable, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub name: String,
+    pub articles_count: i64,
+}
+
+#[derive(Serialize)]
+pub struct FollowedTag {
+    pub name: String,
+    pub following: bool,
+}
+
+#[derive(Insertable)]
+#[table_name = "tags"]
+struct NewTag<'a> {
+    name: &'a str,
+}
+
+#[derive(Insertable)]
+#[table_name = "article_tags"]
+struct NewArticleTag {
+    article: i32,
+    tag: i32,
+    position: i32,
+}
+
+/// Custom validator for `tagList` fields.
+pub fn validate(tags: &[String]) -> Result<(), ValidationError> {
+    if tags
+        .iter()
+        .any(|tag| tag.trim().chars().count() > TAG_MAX_LEN)
+    {
+        return Err(ValidationError::new("is too long"));
+    }
+    Ok(())
+}
+
+/// Trim and lowercase tags, dropping blank and duplicate ones.
+pub fn normalize(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Replace tags of the article. `tags` are expected to be normalized.
+pub fn set(conn: &PgConnection, article_id: i32, tags: &[String]) -> Result<(), AppError> {
+    diesel::delete(article_tags::table.filter(article_tags::article.eq(article_id)))
+        .execute(conn)?;
+    if tags.is_empty() {
+        return Ok(());
+    }
+
+    let new_tags = tags.iter().map(|name| NewTag { name }).collect::<Vec<_>>();
+    diesel::insert_into(tags::table)
+        .values(&new_tags)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    let ids = tags::table
+        .select((tags::name, tags::id))
+        .filter(tags::name.eq_any(tags))
+        .load::<(String, i32)>(conn)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    let article_tags = tags
+        .iter()
+        .zip(0..)
+        .map(|(name, position)| NewArticleTag {
+            article: article_id,
+            tag: ids[name],
+            position,
+        })
+        .collect::<Vec<_>>();
+    diesel::insert_into(article_tags::table)
+        .values(&article_tags)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Tags of the article in the order they were given in.
+pub fn of(conn: &PgConnection, article_id: i32) -> QueryResult<Vec<String>> {
+    Ok(load(conn, &[article_id])?
+        .remove(&article_id)
+        .unwrap_or_default())
+}
+
+/// Tags of the articles by article id, articles without tags are left out.
+pub fn load(conn: &PgConnection, ids: &[i32]) -> QueryResult<HashMap<i32, Vec<String>>> {
+    let rows = article_tags::table
+        .inner_join(tags::table)
+        .select((article_tags::article, tags::name))
+        .filter(article_tags::article.eq_any(ids))
+        .order((article_tags::article, article_tags::position))
+        .load::<(i32, String)>(conn)?;
+
+    let mut result: HashMap<i32, Vec<String>> = HashMap::new();
+    for (article, name) in rows {
+        result.entry(article).or_default().push(name);
+    }
+    Ok(result)
+}
+
+/// Tags of published articles, most popular first.
+pub fn popular(conn: &PgConnection) -> Result<Vec<TagCount>, AppError> {
+    // diesel 1.4 can't mix aggregates with plain columns, hence raw `count(*)`.
+    Ok(tags::table
+        .inner_join(article_tags::table.inner_join(articles::table))
+        .filter(articles::status.eq(ArticleStatus::Published))
+        .group_by((tags::id, tags::name))
+        .select((tags::name, sql::<BigInt>("count(*)")))
+        .order((sql::<BigInt>("count(*)").desc(), tags::name))
+        .load::<TagCount>(conn)?)
+}
+
+/// Follow a tag, so its articles show up in the feed. The tag doesn't have
+/// to be used by any article yet.
+pub fn follow(conn: &PgConnection, name: &str, user_id: i32) -> Result<FollowedTag, AppError> {
+    let name = normalize_one(name)?;
+    conn.transaction(|| {
+        diesel::insert_into(tags::table)
+            .values(&NewTag { name: &name })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        let tag_id = tags::table
+            .select(tags::id)
+            .filter(tags::name.eq(&name))
+            .get_result::<i32>(conn)?;
+
+        diesel::insert_into(tag_follows::table)
+            .values((tag_follows::user.eq(user_id), tag_follows::tag.eq(tag_id)))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(FollowedTag {
+            name,
+            following: true,
+        })
+    })
+}
+
+pub fn unfollow(conn: &PgConnection, name: &str, user_id: i32) -> Result<FollowedTag, AppError> {
+    let name = normalize_one(name)?;
+    diesel::delete(
+        tag_follows::table
+            .filter(tag_follows::user.eq(user_id))
+            .filter(
+                tag_follows::tag.eq_any(tags::table.select(tags::id).filter(tags::name.eq(&name))),
+            ),
+    )
+    .execute(conn)?;
+    Ok(FollowedTag {
+        name,
+        following: false,
+    })
+}
+
+/// Normalize a single tag given in the url.
+fn normalize_one(name: &str) -> Result<String, Errors> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return Err(Errors::new(&[("tag", "can't be blank")]));
+    }
+    if name.chars().count() > TAG_MAX_LEN {
+        return Err(Errors::new(&[("tag", "is too long")]));
+    }
+    Ok(name)
+}
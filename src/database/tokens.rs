@@ -0,0 +1,20 @@
+//! Opaque tokens handed out to clients. Only their hashes get stored, so
+//! a database leak doesn't leak usable tokens.
+
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use sha2::{Digest, Sha256};
+
+pub fn generate(len: usize) -> String {
+    let mut rng = thread_rng();
+    (0..len)
+        .map(|_| rng.sample(Alphanumeric))
+        .map(char::from)
+        .collect()
+}
+
+pub fn hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
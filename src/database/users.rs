@@ -1,3 +1,5 @@
+use crate::database::refresh_tokens;
+use crate::errors::{AppError, Errors};
 use crate::models::user::User;
 use crate::schema::users;
 use diesel::pg::PgConnection;
@@ -17,22 +19,19 @@ pub struct NewUser<'a> {
     pub hash: &'a str,
 }
 
-pub enum UserCreationError {
-    DuplicatedEmail,
-    DuplicatedUsername,
-}
-
-impl From<Error> for UserCreationError {
-    fn from(err: Error) -> UserCreationError {
-        if let Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info) = &err {
-            match info.constraint_name() {
-                Some("users_username_key") => return UserCreationError::DuplicatedUsername,
-                Some("users_email_key") => return UserCreationError::DuplicatedEmail,
-                _ => {}
-            }
+/// Report taken username or email as a validation error of the corresponding field.
+fn user_error(err: Error) -> AppError {
+    if let Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info) = &err {
+        let field = match info.constraint_name() {
+            Some("users_username_key") => Some("username"),
+            Some("users_email_key") => Some("email"),
+            _ => None,
+        };
+        if let Some(field) = field {
+            return Errors::new(&[(field, "has already been taken")]).into();
         }
-        panic!("Error creating user: {:?}", err)
     }
+    err.into()
 }
 
 pub fn create(
@@ -40,13 +39,8 @@ pub fn create(
     username: &str,
     email: &str,
     password: &str,
-) -> Result<User, UserCreationError> {
-    let salt = SaltString::generate(&mut OsRng);
-    let hash = Scrypt
-        .hash_password(password.as_bytes(), &salt)
-        .expect("hash error")
-        .to_string()
-        .to_owned();
+) -> Result<User, AppError> {
+    let hash = hash_password(password)?;
 
     let new_user = &NewUser {
         username,
@@ -57,62 +51,153 @@ pub fn create(
     diesel::insert_into(users::table)
         .values(new_user)
         .get_result::<User>(conn)
-        .map_err(Into::into)
+        .map_err(user_error)
 }
 
-pub fn login(conn: &PgConnection, email: &str, password: &str) -> Option<User> {
+/// Returns `None` if there is no such user or the password doesn't match.
+pub fn login(conn: &PgConnection, email: &str, password: &str) -> Result<Option<User>, AppError> {
     let user = users::table
         .filter(users::email.eq(email))
         .get_result::<User>(conn)
-        .map_err(|err| eprintln!("login_user: {}", err))
-        .ok()?;
-
-    let parsed_hash = PasswordHash::new(&user.hash).unwrap();
-    let password_matches = Scrypt
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .map_err(|err| eprintln!("login_user: scrypt_check: {}", err))
-        .is_ok();
+        .optional()?;
+    let user = match user {
+        Some(user) => user,
+        None => {
+            eprintln!("login attempt failed: no such user");
+            return Ok(None);
+        }
+    };
 
-    if password_matches {
-        Some(user)
+    if verify_password(&user, password)? {
+        Ok(Some(user))
     } else {
         eprintln!(
             "login attempt for '{}' failed: password doesn't match",
             email
         );
-        None
+        Ok(None)
     }
 }
 
-pub fn find(conn: &PgConnection, id: i32) -> Option<User> {
-    users::table
+fn hash_password(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Scrypt
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| {
+            eprintln!("hash_password: {}", err);
+            AppError::Internal
+        })
+}
+
+fn verify_password(user: &User, password: &str) -> Result<bool, AppError> {
+    let parsed_hash = PasswordHash::new(&user.hash).map_err(|err| {
+        eprintln!("verify_password: password_hash: {}", err);
+        AppError::Internal
+    })?;
+    Ok(Scrypt
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .map_err(|err| eprintln!("verify_password: scrypt_check: {}", err))
+        .is_ok())
+}
+
+pub fn find(conn: &PgConnection, id: i32) -> Result<User, AppError> {
+    Ok(users::table.find(id).get_result(conn)?)
+}
+
+pub fn token_version(conn: &PgConnection, id: i32) -> Result<i32, AppError> {
+    Ok(users::table
         .find(id)
-        .get_result(conn)
-        .map_err(|err| println!("find_user: {}", err))
-        .ok()
+        .select(users::token_version)
+        .get_result(conn)?)
 }
 
-// TODO: remove clone when diesel will allow skipping fields
-#[derive(Deserialize, AsChangeset, Default, Clone)]
-#[table_name = "users"]
+/// Invalidate every access and refresh token issued to the user so far.
+pub fn revoke_sessions(conn: &PgConnection, id: i32) -> Result<(), AppError> {
+    conn.transaction(|| {
+        diesel::update(users::table.find(id))
+            .set(users::token_version.eq(users::token_version + 1))
+            .execute(conn)?;
+        refresh_tokens::revoke_all(conn, id)
+    })
+}
+
+#[derive(Deserialize, Validate, Default)]
 pub struct UpdateUserData {
+    #[validate(length(min = 1))]
     username: Option<String>,
+    #[validate(email)]
     email: Option<String>,
     bio: Option<String>,
     image: Option<String>,
-
-    // hack to skip the field
-    #[column_name = "hash"]
+    #[validate(length(min = 8))]
     password: Option<String>,
+    /// Required to change the password.
+    #[serde(rename = "currentPassword")]
+    current_password: Option<String>,
+}
+
+impl UpdateUserData {
+    /// Changing the password revokes every session, see `set_password`.
+    pub fn changes_password(&self) -> bool {
+        self.password.is_some()
+    }
 }
 
-pub fn update(conn: &PgConnection, id: i32, data: &UpdateUserData) -> Option<User> {
-    let data = &UpdateUserData {
-        password: None,
-        ..data.clone()
+#[derive(AsChangeset)]
+#[table_name = "users"]
+struct UserChangeset<'a> {
+    username: Option<&'a str>,
+    email: Option<&'a str>,
+    bio: Option<&'a str>,
+    image: Option<&'a str>,
+}
+
+impl UserChangeset<'_> {
+    /// Diesel refuses to run an update without any columns to set.
+    fn is_empty(&self) -> bool {
+        self.username.is_none()
+            && self.email.is_none()
+            && self.bio.is_none()
+            && self.image.is_none()
+    }
+}
+
+pub fn update(conn: &PgConnection, id: i32, data: &UpdateUserData) -> Result<User, AppError> {
+    if data.password.is_some() {
+        let user = find(conn, id)?;
+        let current_password = data.current_password.as_deref().unwrap_or_default();
+        if !verify_password(&user, current_password)? {
+            return Err(Errors::new(&[("currentPassword", "is invalid")]).into());
+        }
+    }
+
+    let changes = UserChangeset {
+        username: data.username.as_deref(),
+        email: data.email.as_deref(),
+        bio: data.bio.as_deref(),
+        image: data.image.as_deref(),
     };
+
+    conn.transaction(|| {
+        if !changes.is_empty() {
+            diesel::update(users::table.find(id))
+                .set(&changes)
+                .execute(conn)
+                .map_err(user_error)?;
+        }
+        if let Some(ref password) = data.password {
+            set_password(conn, id, password)?;
+        }
+        find(conn, id)
+    })
+}
+
+/// Replace the password hash logging the user out everywhere.
+pub fn set_password(conn: &PgConnection, id: i32, password: &str) -> Result<(), AppError> {
+    let hash = hash_password(password)?;
     diesel::update(users::table.find(id))
-        .set(data)
-        .get_result(conn)
-        .ok()
+        .set(users::hash.eq(hash))
+        .execute(conn)?;
+    revoke_sessions(conn, id)
 }
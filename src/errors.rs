@@ -45,6 +45,65 @@ impl<'r> Responder<'r, 'static> for Errors {
     }
 }
 
+/// Crate-wide error type which every route returns.
+///
+/// Database failures are converted with `?`, so a missing row becomes a 404
+/// and a duplicate a 409 instead of a panicked worker.
+#[derive(Debug)]
+pub enum AppError {
+    /// Request data didn't pass validation.
+    Validation(Errors),
+    NotFound,
+    Unauthorized,
+    Forbidden,
+    Conflict,
+    /// Anything unexpected, the cause is logged on conversion.
+    Internal,
+}
+
+impl From<Errors> for AppError {
+    fn from(errors: Errors) -> Self {
+        AppError::Validation(errors)
+    }
+}
+
+impl From<diesel::result::Error> for AppError {
+    fn from(err: diesel::result::Error) -> Self {
+        use diesel::result::{DatabaseErrorKind, Error};
+
+        match err {
+            Error::NotFound => AppError::NotFound,
+            Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => AppError::Conflict,
+            err => {
+                eprintln!("Database error: {}", err);
+                AppError::Internal
+            }
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for AppError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let (status, reason) = match self {
+            AppError::Validation(errors) => return errors.respond_to(req),
+            AppError::NotFound => (Status::NotFound, "Resource was not found."),
+            AppError::Unauthorized => (Status::Unauthorized, "Invalid or expired token."),
+            AppError::Forbidden => (Status::Forbidden, "Operation is not permitted."),
+            AppError::Conflict => (Status::Conflict, "Resource already exists."),
+            AppError::Internal => (Status::InternalServerError, "Internal server error."),
+        };
+
+        status::Custom(
+            status,
+            Json(json!({
+                "status": "error",
+                "reason": reason,
+            })),
+        )
+        .respond_to(req)
+    }
+}
+
 pub struct FieldValidator {
     errors: ValidationErrors,
 }
@@ -22,8 +22,11 @@ mod auth;
 mod config;
 mod database;
 mod errors;
+mod mailer;
+mod markdown;
 mod models;
 mod routes;
+mod scheduler;
 mod schema;
 
 #[catch(404)]
@@ -40,6 +43,17 @@ fn cors_fairing() -> Cors {
         .expect("Cors fairing cannot be created")
 }
 
+/// Fix `favorites_count` of articles, returns how many were off.
+pub fn recount_favorites() -> Result<usize, Box<dyn std::error::Error>> {
+    use diesel::Connection;
+
+    dotenv().ok();
+    let database_url = std::env::var("DATABASE_URL")
+        .map_err(|err| format!("No DATABASE_URL environment variable found: {}", err))?;
+    let conn = diesel::PgConnection::establish(&database_url)?;
+    Ok(database::reactions::recount_favorites(&conn)?)
+}
+
 #[launch]
 pub fn rocket() -> _ {
     dotenv().ok();
@@ -49,6 +63,11 @@ pub fn rocket() -> _ {
             routes![
                 routes::users::post_users,
                 routes::users::post_users_login,
+                routes::users::post_users_refresh,
+                routes::users::post_users_logout,
+                routes::users::post_users_logout_all,
+                routes::users::post_password_reset,
+                routes::users::post_password_reset_confirm,
                 routes::users::put_user,
                 routes::users::get_user,
                 routes::articles::post_articles,
@@ -57,12 +76,23 @@ pub fn rocket() -> _ {
                 routes::articles::delete_article,
                 routes::articles::favorite_article,
                 routes::articles::unfavorite_article,
+                routes::articles::react_article,
+                routes::articles::unreact_article,
+                routes::articles::publish_article,
+                routes::articles::get_revisions,
+                routes::articles::get_revision,
+                routes::articles::get_revision_diff,
+                routes::articles::restore_revision,
                 routes::articles::get_articles,
                 routes::articles::get_articles_feed,
+                routes::articles::search_articles,
                 routes::articles::post_comment,
+                routes::articles::put_comment,
                 routes::articles::get_comments,
                 routes::articles::delete_comment,
                 routes::tags::get_tags,
+                routes::tags::follow_tag,
+                routes::tags::unfollow_tag,
                 routes::profiles::get_profile,
                 routes::profiles::follow,
                 routes::profiles::unfollow,
@@ -71,5 +101,7 @@ pub fn rocket() -> _ {
         .attach(database::Db::fairing())
         .attach(cors_fairing())
         .attach(config::AppState::manage())
+        .attach(mailer::manage())
+        .attach(scheduler::fairing())
         .register("/", catchers![not_found])
 }
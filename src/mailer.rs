@@ -0,0 +1,61 @@
+use rocket::fairing::AdHoc;
+use serde::Serialize;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers mails to users. Managed by rocket as `Box<dyn Mailer>`.
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &Mail) -> io::Result<()>;
+}
+
+/// Print mails to stdout. Debug only, for local development.
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, mail: &Mail) -> io::Result<()> {
+        println!(
+            "Mail to {}\nSubject: {}\n\n{}",
+            mail.to, mail.subject, mail.body
+        );
+        Ok(())
+    }
+}
+
+/// Append mails to a file, one json object per line.
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: &Mail) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let line = serde_json::to_string(mail)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+/// Manage `FileMailer` writing to `MAIL_FILE` if it's set, or `LogMailer` otherwise.
+///
+/// Mails carry password reset tokens, so release builds refuse to log them.
+pub fn manage() -> AdHoc {
+    AdHoc::on_ignite("Manage mailer", |rocket| async move {
+        let mailer: Box<dyn Mailer> = match env::var("MAIL_FILE") {
+            Ok(path) => Box::new(FileMailer { path: path.into() }),
+            Err(_) if cfg!(debug_assertions) => Box::new(LogMailer),
+            Err(err) => panic!("No MAIL_FILE environment variable found: {:?}", err),
+        };
+        rocket.manage(mailer)
+    })
+}
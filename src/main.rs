@@ -1,7 +1,26 @@
 use realworld;
 use rocket;
+use std::env;
+use std::process;
 
 #[rocket::main]
 async fn main() -> Result<(), rocket::Error> {
-    realworld::rocket().launch().await
+    let command = env::args().nth(1);
+    match command.as_deref() {
+        None => realworld::rocket().launch().await,
+        Some("recount-favorites") => match realworld::recount_favorites() {
+            Ok(fixed) => {
+                println!("Fixed favorites count of {} articles", fixed);
+                Ok(())
+            }
+            Err(err) => {
+                eprintln!("Cannot recount favorites: {}", err);
+                process::exit(1);
+            }
+        },
+        Some(command) => {
+            eprintln!("Unknown command: {}, try recount-favorites", command);
+            process::exit(2);
+        }
+    }
 }
@@ -0,0 +1,94 @@
+use ammonia::Builder;
+use pulldown_cmark::{html, Event, Options, Parser};
+
+/// Render markdown to html which is safe to embed in a page.
+///
+/// Scripts, event handler attributes and links with unsafe schemes like
+/// `javascript:` are stripped, remaining links get `rel="nofollow"`.
+pub fn render(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+
+    let mut unsafe_html = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, Parser::new_ext(markdown, options));
+
+    Builder::default()
+        .link_rel(Some("nofollow noopener noreferrer"))
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+/// Html of `body`, unless it's `cached` already.
+pub fn html(cached: Option<String>, body: &str) -> String {
+    cached.unwrap_or_else(|| render(body))
+}
+
+/// Text of the markdown without any markup, with whitespace collapsed.
+pub fn plain_text(markdown: &str) -> String {
+    let mut text = String::with_capacity(markdown.len());
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(chunk) | Event::Code(chunk) => text.push_str(&chunk),
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// At most `max_chars` of the text, cut at a word boundary.
+pub fn excerpt(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut excerpt = String::new();
+    for word in text.split_whitespace() {
+        if excerpt.chars().count() + word.chars().count() + 1 > max_chars {
+            break;
+        }
+        if !excerpt.is_empty() {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(word);
+    }
+    if excerpt.is_empty() {
+        excerpt = text.chars().take(max_chars).collect();
+    }
+    excerpt.push('…');
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(render("*Hello*"), "<p><em>Hello</em></p>\n");
+    }
+
+    #[test]
+    fn test_render_strips_unsafe_html() {
+        let html = render("<script>alert(1)</script><img src=x onerror=alert(1)>");
+        assert!(!html.contains("script"));
+        assert!(!html.contains("onerror"));
+
+        let html = render("[click](javascript:alert(1))");
+        assert!(!html.contains("javascript"));
+    }
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(
+            plain_text("# Title\n\nSome *emphasis* and `code`.\n\n- one\n- two"),
+            "Title Some emphasis and code. one two"
+        );
+    }
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("short text", 20), "short text");
+        assert_eq!(excerpt("one two three four", 10), "one two…");
+    }
+}
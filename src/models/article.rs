@@ -1,7 +1,53 @@
 use crate::config::DATE_FORMAT;
-use crate::models::user::User;
+use crate::markdown;
+use crate::models::reaction::{ReactionKind, Reactions};
+use crate::models::user::Profile;
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+/// Only published articles are visible to everyone but the author.
+#[derive(Debug, Clone, Copy, PartialEq, AsExpression, FromSqlRow, Serialize, Deserialize)]
+#[sql_type = "Text"]
+#[serde(rename_all = "lowercase")]
+pub enum ArticleStatus {
+    Draft,
+    Published,
+    /// Becomes published at `published_at`
+    Scheduled,
+}
+
+impl ArticleStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ArticleStatus::Draft => "draft",
+            ArticleStatus::Published => "published",
+            ArticleStatus::Scheduled => "scheduled",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for ArticleStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Text, Pg>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for ArticleStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Pg>>::from_sql(bytes)?.as_str() {
+            "draft" => Ok(ArticleStatus::Draft),
+            "published" => Ok(ArticleStatus::Published),
+            "scheduled" => Ok(ArticleStatus::Scheduled),
+            status => Err(format!("Unknown article status: {}", status).into()),
+        }
+    }
+}
 
 #[derive(Queryable)]
 pub struct Article {
@@ -11,14 +57,24 @@ pub struct Article {
     pub description: String,
     pub body: String,
     pub author: i32,
-    pub tag_list: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub favorites_count: i32,
+    pub status: ArticleStatus,
+    pub published_at: Option<DateTime<Utc>>,
+    pub body_html: Option<String>,
+    pub word_count: i32,
+    pub reading_time_minutes: i32,
+    pub excerpt: String,
 }
 
 impl Article {
-    pub fn attach(self, author: User, favorited: bool) -> ArticleJson {
+    pub fn attach(
+        self,
+        author: Profile,
+        tag_list: Vec<String>,
+        reactions: Reactions,
+    ) -> ArticleJson {
         ArticleJson {
             id: self.id,
             slug: self.slug,
@@ -26,11 +82,22 @@ impl Article {
             description: self.description,
             body: self.body,
             author,
-            tag_list: self.tag_list,
+            tag_list,
             created_at: self.created_at.format(DATE_FORMAT).to_string(),
             updated_at: self.updated_at.format(DATE_FORMAT).to_string(),
             favorites_count: self.favorites_count,
-            favorited,
+            favorited: reactions.mine.contains(&ReactionKind::Like),
+            reactions: reactions.counts,
+            my_reactions: reactions.mine,
+            status: self.status,
+            published_at: self
+                .published_at
+                .map(|published_at| published_at.format(DATE_FORMAT).to_string()),
+            body_html: None,
+            cached_html: self.body_html,
+            word_count: self.word_count,
+            reading_time_minutes: self.reading_time_minutes,
+            excerpt: self.excerpt,
         }
     }
 }
@@ -43,10 +110,44 @@ pub struct ArticleJson {
     pub title: String,
     pub description: String,
     pub body: String,
-    pub author: User,
+    pub author: Profile,
     pub tag_list: Vec<String>,
     pub created_at: String,
     pub updated_at: String,
     pub favorites_count: i32,
     pub favorited: bool,
+    /// Number of reactions by kind
+    pub reactions: BTreeMap<ReactionKind, i64>,
+    /// Reactions of the viewer, `like` among them when `favorited`
+    pub my_reactions: BTreeSet<ReactionKind>,
+    pub status: ArticleStatus,
+    pub published_at: Option<String>,
+    /// Rendered `body`, only present when asked for with `with_html`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_html: Option<String>,
+    /// Stored html of `body`, if any
+    #[serde(skip)]
+    cached_html: Option<String>,
+    pub word_count: i32,
+    pub reading_time_minutes: i32,
+    /// `description`, or the start of `body` if there is none
+    pub excerpt: String,
+}
+
+impl ArticleJson {
+    /// Fill in `body_html` if asked for.
+    pub fn with_html(mut self, html: bool) -> Self {
+        if html {
+            self.body_html = Some(markdown::html(self.cached_html.take(), &self.body));
+        }
+        self
+    }
+}
+
+/// Article found by full-text search, with matches highlighted in `snippet`.
+#[derive(Serialize)]
+pub struct SearchResultJson {
+    #[serde(flatten)]
+    pub article: ArticleJson,
+    pub snippet: String,
 }
@@ -1,5 +1,6 @@
 use crate::config::DATE_FORMAT;
-use crate::models::user::User;
+use crate::markdown;
+use crate::models::user::Profile;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 
@@ -11,16 +12,29 @@ pub struct Comment {
     pub author: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub body_html: Option<String>,
+    pub parent: Option<i32>,
+    /// Set when deleted while having replies, `body` holds a tombstone then.
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Comment {
-    pub fn attach(self, author: User) -> CommentJson {
+    pub fn attach(self, author: Profile) -> CommentJson {
         CommentJson {
             id: self.id,
             body: self.body,
-            author,
+            author: match self.deleted_at {
+                Some(_) => None,
+                None => Some(author),
+            },
             created_at: self.created_at.format(DATE_FORMAT).to_string(),
             updated_at: self.updated_at.format(DATE_FORMAT).to_string(),
+            body_html: None,
+            cached_html: self.body_html,
+            parent_id: self.parent,
+            depth: 0,
+            deleted: self.deleted_at.is_some(),
+            edited: self.updated_at > self.created_at,
         }
     }
 }
@@ -30,7 +44,29 @@ impl Comment {
 pub struct CommentJson {
     pub id: i32,
     pub body: String,
-    pub author: User,
+    /// Left out of tombstones
+    pub author: Option<Profile>,
     pub created_at: String,
     pub updated_at: String,
+    /// Same as `ArticleJson::body_html`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_html: Option<String>,
+    #[serde(skip)]
+    cached_html: Option<String>,
+    pub parent_id: Option<i32>,
+    /// Nesting level in the thread, 0 for top level comments
+    pub depth: i32,
+    pub deleted: bool,
+    /// Whether the body was changed after posting
+    pub edited: bool,
+}
+
+impl CommentJson {
+    /// See `ArticleJson::with_html`.
+    pub fn with_html(mut self, html: bool) -> Self {
+        if html {
+            self.body_html = Some(markdown::html(self.cached_html.take(), &self.body));
+        }
+        self
+    }
 }
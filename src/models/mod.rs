@@ -1,3 +1,5 @@
 pub mod article;
 pub mod comment;
+pub mod reaction;
+pub mod revision;
 pub mod user;
@@ -0,0 +1,95 @@
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use rocket::request::FromParam;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::str::FromStr;
+
+/// Reaction to an article, `Like` is what favoriting does.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, AsExpression, FromSqlRow, Serialize,
+)]
+#[sql_type = "Text"]
+#[serde(rename_all = "lowercase")]
+pub enum ReactionKind {
+    Like,
+    Clap,
+    Insightful,
+    Heart,
+    Laugh,
+}
+
+impl ReactionKind {
+    pub const ALL: [ReactionKind; 5] = [
+        ReactionKind::Like,
+        ReactionKind::Clap,
+        ReactionKind::Insightful,
+        ReactionKind::Heart,
+        ReactionKind::Laugh,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReactionKind::Like => "like",
+            ReactionKind::Clap => "clap",
+            ReactionKind::Insightful => "insightful",
+            ReactionKind::Heart => "heart",
+            ReactionKind::Laugh => "laugh",
+        }
+    }
+}
+
+impl FromStr for ReactionKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReactionKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.as_str() == s)
+            .ok_or(())
+    }
+}
+
+/// Unknown kinds don't match the route.
+impl<'a> FromParam<'a> for ReactionKind {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        param.parse().map_err(|_| param)
+    }
+}
+
+impl ToSql<Text, Pg> for ReactionKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Text, Pg>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for ReactionKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let kind = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        kind.parse()
+            .map_err(|_| format!("Unknown reaction kind: {}", kind).into())
+    }
+}
+
+/// Reactions to an article as seen by a user.
+pub struct Reactions {
+    /// Number of reactions of every kind, including those nobody used yet
+    pub counts: BTreeMap<ReactionKind, i64>,
+    /// Reactions of the user
+    pub mine: BTreeSet<ReactionKind>,
+}
+
+impl Default for Reactions {
+    fn default() -> Self {
+        Reactions {
+            counts: ReactionKind::ALL.iter().map(|&kind| (kind, 0)).collect(),
+            mine: BTreeSet::new(),
+        }
+    }
+}
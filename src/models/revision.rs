@@ -0,0 +1,57 @@
+use crate::config::DATE_FORMAT;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Previous version of an article, saved on every update.
+#[derive(Queryable)]
+pub struct Revision {
+    pub number: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub tag_list: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Revision {
+    pub fn into_json(self) -> RevisionJson {
+        RevisionJson {
+            number: self.number,
+            title: self.title,
+            description: self.description,
+            body: self.body,
+            tag_list: self.tag_list,
+            created_at: self.created_at.format(DATE_FORMAT).to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionJson {
+    pub number: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub tag_list: Vec<String>,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "op", content = "line", rename_all = "lowercase")]
+pub enum DiffLine {
+    Equal(String),
+    Insert(String),
+    Delete(String),
+}
+
+/// Line-level changes from revision `from` to revision `to`, or to the
+/// current version if `to` is `None`.
+#[derive(Serialize)]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: Option<i32>,
+    pub title: Vec<DiffLine>,
+    pub description: Vec<DiffLine>,
+    pub body: Vec<DiffLine>,
+}
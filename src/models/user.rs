@@ -1,20 +1,25 @@
 use crate::auth::Auth;
-use chrono::{Duration, Utc};
+use crate::config::AppState;
+use chrono::Utc;
 use serde::Serialize;
 
 type Url = String;
 
-#[derive(Queryable, Serialize)]
+/// A user as stored, deliberately not `Serialize`: respond with `UserAuth`
+/// to the user themselves and with `Profile` to anybody else.
+#[derive(Queryable)]
 pub struct User {
     pub id: i32,
     pub username: String,
     pub email: String,
     pub bio: Option<String>,
     pub image: Option<Url>,
-    #[serde(skip_serializing)]
     pub hash: String,
+    /// Tokens with another version are rejected, see `Auth`.
+    pub token_version: i32,
 }
 
+/// The authenticated user, including private fields like `email`.
 #[derive(Serialize)]
 pub struct UserAuth<'a> {
     username: &'a str,
@@ -22,8 +27,20 @@ pub struct UserAuth<'a> {
     bio: Option<&'a str>,
     image: Option<&'a str>,
     token: String,
+    #[serde(rename = "refreshToken", skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
 }
 
+impl UserAuth<'_> {
+    pub fn with_refresh_token(self, refresh_token: String) -> Self {
+        UserAuth {
+            refresh_token: Some(refresh_token),
+            ..self
+        }
+    }
+}
+
+/// Public view of a user, e.g. as an article or comment author.
 #[derive(Serialize)]
 pub struct Profile {
     username: String,
@@ -33,14 +50,15 @@ pub struct Profile {
 }
 
 impl User {
-    pub fn to_user_auth(&self, secret: &[u8]) -> UserAuth {
-        let exp = Utc::now() + Duration::days(60); // TODO: move to config
+    pub fn to_user_auth(&self, state: &AppState) -> UserAuth {
+        let exp = Utc::now() + state.access_token_lifetime;
         let token = Auth {
             id: self.id,
             username: self.username.clone(),
+            version: self.token_version,
             exp: exp.timestamp(),
         }
-        .token(secret);
+        .token(&state.secret);
 
         UserAuth {
             username: &self.username,
@@ -48,6 +66,7 @@ impl User {
             bio: self.bio.as_ref().map(String::as_str),
             image: self.image.as_ref().map(String::as_str),
             token,
+            refresh_token: None,
         }
     }
 
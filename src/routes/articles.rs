@@ -1,8 +1,15 @@
 use crate::auth::Auth;
-use crate::database::articles::{FeedArticles, FindArticles};
+use crate::config::AppState;
+use crate::database::articles::{
+    ArticlesPage, FeedArticles, FindArticles, Publication, SearchArticles,
+};
+use crate::database::comments::FindComments;
 use crate::database::{self, Db};
-use crate::errors::{Errors, FieldValidator};
+use crate::errors::{AppError, FieldValidator};
+use crate::models::reaction::ReactionKind;
+use rocket::http::Header;
 use rocket::serde::json::{json, Json, Value};
+use rocket::State;
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -14,12 +21,15 @@ pub struct NewArticle {
 pub struct NewArticleData {
     #[validate(length(min = 1))]
     title: Option<String>,
-    #[validate(length(min = 1))]
+    /// may be left out, `excerpt` is generated from `body` then
     description: Option<String>,
     #[validate(length(min = 1))]
     body: Option<String>,
     #[serde(rename = "tagList")]
+    #[validate(custom = "database::tags::validate")]
     tag_list: Vec<String>,
+    #[serde(flatten)]
+    publication: Publication,
 }
 
 #[post("/articles", format = "json", data = "<new_article>")]
@@ -27,15 +37,17 @@ pub async fn post_articles(
     auth: Auth,
     new_article: Json<NewArticle>,
     db: Db,
-) -> Result<Value, Errors> {
+    state: &State<AppState>,
+) -> Result<Value, AppError> {
     let new_article = new_article.into_inner().article;
 
     let mut extractor = FieldValidator::validate(&new_article);
     let title = extractor.extract("title", new_article.title);
-    let description = extractor.extract("description", new_article.description);
+    let description = new_article.description.unwrap_or_default();
     let body = extractor.extract("body", new_article.body);
     extractor.check()?;
 
+    let slug_strategy = state.slug_strategy;
     let article = db
         .run(move |conn| {
             database::articles::create(
@@ -45,52 +57,201 @@ pub async fn post_articles(
                 &description,
                 &body,
                 &new_article.tag_list,
+                &new_article.publication,
+                slug_strategy,
             )
         })
-        .await;
+        .await?;
     Ok(json!({ "article": article }))
 }
 
-/// return multiple articles, ordered by most recent first
+/// return multiple articles, ordered by most recent first unless `sort` says otherwise
 #[get("/articles?<params..>")]
-pub async fn get_articles(params: FindArticles, auth: Option<Auth>, db: Db) -> Value {
+pub async fn get_articles(
+    params: FindArticles,
+    auth: Option<Auth>,
+    db: Db,
+) -> Result<Value, AppError> {
     let user_id = auth.map(|x| x.id);
-    let articles = db
-        .run(move |conn| database::articles::find(conn, &params, user_id))
-        .await;
-    json!({ "articles": articles.0, "articlesCount": articles.1 })
+    let html = params.html;
+    db.run(move |conn| database::articles::find(conn, &params, user_id))
+        .await
+        .map(|page| to_articles_json(page, html))
 }
 
-#[get("/articles/<slug>")]
-pub async fn get_article(slug: String, auth: Option<Auth>, db: Db) -> Option<Value> {
+/// full-text search over title, description and body, most relevant first
+#[get("/articles/search?<params..>")]
+pub async fn search_articles(
+    params: SearchArticles,
+    auth: Option<Auth>,
+    db: Db,
+) -> Result<Value, AppError> {
     let user_id = auth.map(|x| x.id);
-    db.run(move |conn| database::articles::find_one(conn, &slug, user_id))
-        .await
-        .map(|article| json!({ "article": article }))
+    let html = params.html;
+    let (articles, count) = db
+        .run(move |conn| database::articles::search(conn, &params, user_id))
+        .await?;
+    let articles: Vec<_> = articles
+        .into_iter()
+        .map(|mut result| {
+            result.article = result.article.with_html(html);
+            result
+        })
+        .collect();
+    Ok(json!({ "articles": articles, "articlesCount": count }))
 }
 
-#[delete("/articles/<slug>")]
-pub async fn delete_article(slug: String, auth: Auth, db: Db) {
-    db.run(move |conn| {
-        database::articles::delete(conn, &slug, auth.id);
+fn to_articles_json(page: ArticlesPage, html: bool) -> Value {
+    let articles: Vec<_> = page
+        .articles
+        .into_iter()
+        .map(|article| article.with_html(html))
+        .collect();
+    json!({
+        "articles": articles,
+        "articlesCount": page.count,
+        "nextCursor": page.next_cursor,
+        "prevCursor": page.prev_cursor,
     })
-    .await;
+}
+
+#[derive(Responder)]
+pub enum ArticleResponse {
+    Found(Value),
+    /// Requested by an old slug, `Location` points to the current one.
+    #[response(status = 301)]
+    Moved(Value, Header<'static>),
+}
+
+/// `html` includes rendered `bodyHtml`
+#[get("/articles/<slug>?<html>")]
+pub async fn get_article(
+    slug: String,
+    html: bool,
+    auth: Option<Auth>,
+    db: Db,
+) -> Result<ArticleResponse, AppError> {
+    let user_id = auth.map(|x| x.id);
+    let requested = slug.clone();
+    let article = db
+        .run(move |conn| database::articles::find_one(conn, &slug, user_id))
+        .await?
+        .with_html(html);
+
+    if article.slug == requested {
+        Ok(ArticleResponse::Found(json!({ "article": article })))
+    } else {
+        let location = Header::new("Location", format!("/api/articles/{}", article.slug));
+        Ok(ArticleResponse::Moved(
+            json!({ "article": article }),
+            location,
+        ))
+    }
+}
+
+#[delete("/articles/<slug>")]
+pub async fn delete_article(slug: String, auth: Auth, db: Db) -> Result<(), AppError> {
+    db.run(move |conn| database::articles::delete(conn, &slug, auth.id))
+        .await
 }
 
 #[post("/articles/<slug>/favorite")]
-pub async fn favorite_article(slug: String, auth: Auth, db: Db) -> Option<Value> {
+pub async fn favorite_article(slug: String, auth: Auth, db: Db) -> Result<Value, AppError> {
     db.run(move |conn| database::articles::favorite(conn, &slug, auth.id))
         .await
         .map(|article| json!({ "article": article }))
 }
 
 #[delete("/articles/<slug>/favorite")]
-pub async fn unfavorite_article(slug: String, auth: Auth, db: Db) -> Option<Value> {
+pub async fn unfavorite_article(slug: String, auth: Auth, db: Db) -> Result<Value, AppError> {
     db.run(move |conn| database::articles::unfavorite(conn, &slug, auth.id))
         .await
         .map(|article| json!({ "article": article }))
 }
 
+/// `kind` is one of `like`, `clap`, `insightful`, `heart` or `laugh`.
+#[post("/articles/<slug>/reactions/<kind>")]
+pub async fn react_article(
+    slug: String,
+    kind: ReactionKind,
+    auth: Auth,
+    db: Db,
+) -> Result<Value, AppError> {
+    db.run(move |conn| database::articles::react(conn, &slug, auth.id, kind))
+        .await
+        .map(|article| json!({ "article": article }))
+}
+
+#[delete("/articles/<slug>/reactions/<kind>")]
+pub async fn unreact_article(
+    slug: String,
+    kind: ReactionKind,
+    auth: Auth,
+    db: Db,
+) -> Result<Value, AppError> {
+    db.run(move |conn| database::articles::unreact(conn, &slug, auth.id, kind))
+        .await
+        .map(|article| json!({ "article": article }))
+}
+
+#[post("/articles/<slug>/publish")]
+pub async fn publish_article(slug: String, auth: Auth, db: Db) -> Result<Value, AppError> {
+    db.run(move |conn| database::articles::publish(conn, &slug, auth.id))
+        .await
+        .map(|article| json!({ "article": article }))
+}
+
+#[get("/articles/<slug>/revisions")]
+pub async fn get_revisions(slug: String, auth: Option<Auth>, db: Db) -> Result<Value, AppError> {
+    let user_id = auth.map(|x| x.id);
+    let revisions = db
+        .run(move |conn| database::revisions::list(conn, &slug, user_id))
+        .await?;
+    Ok(json!({ "revisions": revisions }))
+}
+
+#[get("/articles/<slug>/revisions/<number>")]
+pub async fn get_revision(
+    slug: String,
+    number: i32,
+    auth: Option<Auth>,
+    db: Db,
+) -> Result<Value, AppError> {
+    let user_id = auth.map(|x| x.id);
+    db.run(move |conn| database::revisions::find(conn, &slug, number, user_id))
+        .await
+        .map(|revision| json!({ "revision": revision }))
+}
+
+/// diff the revision against revision `to`, or against the current version
+#[get("/articles/<slug>/revisions/<number>/diff?<to>")]
+pub async fn get_revision_diff(
+    slug: String,
+    number: i32,
+    to: Option<i32>,
+    auth: Option<Auth>,
+    db: Db,
+) -> Result<Value, AppError> {
+    let user_id = auth.map(|x| x.id);
+    db.run(move |conn| database::revisions::diff(conn, &slug, number, to, user_id))
+        .await
+        .map(|diff| json!({ "diff": diff }))
+}
+
+#[post("/articles/<slug>/revisions/<number>/restore")]
+pub async fn restore_revision(
+    slug: String,
+    number: i32,
+    auth: Auth,
+    db: Db,
+    state: &State<AppState>,
+) -> Result<Value, AppError> {
+    let slug_strategy = state.slug_strategy;
+    db.run(move |conn| database::revisions::restore(conn, &slug, number, auth.id, slug_strategy))
+        .await
+        .map(|article| json!({ "article": article }))
+}
+
 #[derive(Deserialize)]
 pub struct UpdateArticle {
     article: database::articles::UpdateArticleData,
@@ -102,13 +263,15 @@ pub async fn put_articles(
     article: Json<UpdateArticle>,
     auth: Auth,
     db: Db,
-) -> Option<Value> {
-    // TODO: check auth
-    db.run(move |conn| {
-        database::articles::update(conn, &slug, auth.id, article.into_inner().article)
-    })
-    .await
-    .map(|article| json!({ "article": article }))
+    state: &State<AppState>,
+) -> Result<Value, AppError> {
+    let article = article.into_inner().article;
+    FieldValidator::validate(&article).check()?;
+
+    let slug_strategy = state.slug_strategy;
+    db.run(move |conn| database::articles::update(conn, &slug, auth.id, article, slug_strategy))
+        .await
+        .map(|article| json!({ "article": article }))
 }
 
 #[derive(Deserialize)]
@@ -120,6 +283,9 @@ pub struct NewComment {
 pub struct NewCommentData {
     #[validate(length(min = 1))]
     body: Option<String>,
+    /// comment to reply to
+    #[serde(rename = "parentId")]
+    parent_id: Option<i32>,
 }
 
 #[post("/articles/<slug>/comments", format = "json", data = "<new_comment>")]
@@ -128,7 +294,7 @@ pub async fn post_comment(
     new_comment: Json<NewComment>,
     auth: Auth,
     db: Db,
-) -> Result<Value, Errors> {
+) -> Result<Value, AppError> {
     let new_comment = new_comment.into_inner().comment;
 
     let mut extractor = FieldValidator::validate(&new_comment);
@@ -136,30 +302,78 @@ pub async fn post_comment(
     extractor.check()?;
 
     let comment = db
-        .run(move |conn| database::comments::create(conn, auth.id, &slug, &body))
-        .await;
+        .run(move |conn| {
+            database::comments::create(conn, auth.id, &slug, &body, new_comment.parent_id)
+        })
+        .await?;
+    Ok(json!({ "comment": comment }))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateComment {
+    comment: UpdateCommentData,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateCommentData {
+    #[validate(length(min = 1))]
+    body: Option<String>,
+}
+
+#[put("/articles/<slug>/comments/<id>", format = "json", data = "<comment>")]
+pub async fn put_comment(
+    slug: String,
+    id: i32,
+    comment: Json<UpdateComment>,
+    auth: Auth,
+    db: Db,
+) -> Result<Value, AppError> {
+    let comment = comment.into_inner().comment;
+
+    let mut extractor = FieldValidator::validate(&comment);
+    let body = extractor.extract("body", comment.body);
+    extractor.check()?;
+
+    let comment = db
+        .run(move |conn| database::comments::update(conn, auth.id, &slug, id, &body))
+        .await?;
     Ok(json!({ "comment": comment }))
 }
 
 #[delete("/articles/<slug>/comments/<id>")]
-pub async fn delete_comment(slug: String, id: i32, auth: Auth, db: Db) {
+pub async fn delete_comment(slug: String, id: i32, auth: Auth, db: Db) -> Result<(), AppError> {
     db.run(move |conn| database::comments::delete(conn, auth.id, &slug, id))
         .await
 }
 
-#[get("/articles/<slug>/comments")]
-pub async fn get_comments(slug: String, db: Db) -> Value {
-    let comments = db
-        .run(move |conn| database::comments::find_by_slug(conn, &slug))
-        .await;
-    json!({ "comments": comments })
+/// `commentsCount` is the number of top level comments, which are paged.
+#[get("/articles/<slug>/comments?<params..>")]
+pub async fn get_comments(
+    slug: String,
+    params: FindComments,
+    auth: Option<Auth>,
+    db: Db,
+) -> Result<Value, AppError> {
+    let user_id = auth.map(|x| x.id);
+    let html = params.html;
+    let (comments, count) = db
+        .run(move |conn| database::comments::find_by_slug(conn, &slug, &params, user_id))
+        .await?;
+    let comments: Vec<_> = comments
+        .into_iter()
+        .map(|comment| comment.with_html(html))
+        .collect();
+    Ok(json!({ "comments": comments, "commentsCount": count }))
 }
 
 #[get("/articles/feed?<params..>")]
-pub async fn get_articles_feed(params: FeedArticles, auth: Auth, db: Db) -> Value {
-    let articles = db
-        .run(move |conn| database::articles::feed(conn, &params, auth.id))
-        .await;
-    let articles_count = articles.len();
-    json!({ "articles": articles, "articlesCount": articles_count })
+pub async fn get_articles_feed(
+    params: FeedArticles,
+    auth: Auth,
+    db: Db,
+) -> Result<Value, AppError> {
+    let html = params.html;
+    db.run(move |conn| database::articles::feed(conn, &params, auth.id))
+        .await
+        .map(|page| to_articles_json(page, html))
 }
@@ -1,5 +1,6 @@
 use crate::auth::Auth;
 use crate::database::{self, Db};
+use crate::errors::AppError;
 use crate::models::user::Profile;
 use rocket::serde::json::{json, Value};
 
@@ -8,7 +9,7 @@ fn to_profile_json(profile: Profile) -> Value {
 }
 
 #[get("/profiles/<username>")]
-pub async fn get_profile(username: String, auth: Option<Auth>, db: Db) -> Option<Value> {
+pub async fn get_profile(username: String, auth: Option<Auth>, db: Db) -> Result<Value, AppError> {
     let user_id = auth.map(|auth| auth.id);
     db.run(move |conn| database::profiles::find(conn, &username, user_id))
         .await
@@ -16,14 +17,14 @@ pub async fn get_profile(username: String, auth: Option<Auth>, db: Db) -> Option
 }
 
 #[post("/profiles/<username>/follow")]
-pub async fn follow(username: String, auth: Auth, db: Db) -> Option<Value> {
+pub async fn follow(username: String, auth: Auth, db: Db) -> Result<Value, AppError> {
     db.run(move |conn| database::profiles::follow(conn, &username, auth.id))
         .await
         .map(to_profile_json)
 }
 
 #[delete("/profiles/<username>/follow")]
-pub async fn unfollow(username: String, auth: Auth, db: Db) -> Option<Value> {
+pub async fn unfollow(username: String, auth: Auth, db: Db) -> Result<Value, AppError> {
     db.run(move |conn| database::profiles::unfollow(conn, &username, auth.id))
         .await
         .map(to_profile_json)
@@ -1,8 +1,32 @@
+use crate::auth::Auth;
+use crate::database::tags::FollowedTag;
 use crate::database::{self, Db};
+use crate::errors::AppError;
 use rocket::serde::json::{json, Value};
 
+fn to_tag_json(tag: FollowedTag) -> Value {
+    json!({ "tag": tag })
+}
+
+/// Tags in use, most popular first. `tags` keeps the plain list for clients
+/// that don't need the counts.
 #[get("/tags")]
-pub async fn get_tags(db: Db) -> Value {
-    let tags = db.run(move |conn| database::articles::tags(conn)).await;
-    json!({ "tags": tags })
+pub async fn get_tags(db: Db) -> Result<Value, AppError> {
+    let counts = db.run(move |conn| database::tags::popular(conn)).await?;
+    let tags: Vec<&str> = counts.iter().map(|tag| tag.name.as_str()).collect();
+    Ok(json!({ "tags": tags, "tagCounts": counts }))
+}
+
+#[post("/tags/<tag>/follow")]
+pub async fn follow_tag(tag: String, auth: Auth, db: Db) -> Result<Value, AppError> {
+    db.run(move |conn| database::tags::follow(conn, &tag, auth.id))
+        .await
+        .map(to_tag_json)
+}
+
+#[delete("/tags/<tag>/follow")]
+pub async fn unfollow_tag(tag: String, auth: Auth, db: Db) -> Result<Value, AppError> {
+    db.run(move |conn| database::tags::unfollow(conn, &tag, auth.id))
+        .await
+        .map(to_tag_json)
 }
@@ -1,7 +1,8 @@
 use crate::auth::Auth;
 use crate::config::AppState;
-use crate::database::{self, users::UserCreationError, Db};
-use crate::errors::{Errors, FieldValidator};
+use crate::database::{self, Db};
+use crate::errors::{AppError, Errors, FieldValidator};
+use crate::mailer::{Mail, Mailer};
 
 use rocket::serde::json::{json, Json, Value};
 use rocket::State;
@@ -27,7 +28,7 @@ pub async fn post_users(
     new_user: Json<NewUser>,
     db: Db,
     state: &State<AppState>,
-) -> Result<Value, Errors> {
+) -> Result<Value, AppError> {
     let new_user = new_user.into_inner().user;
 
     let mut extractor = FieldValidator::validate(&new_user);
@@ -36,19 +37,15 @@ pub async fn post_users(
     let password = extractor.extract("password", new_user.password);
 
     extractor.check()?;
-    let secret = state.secret.clone();
-    db.run(move |conn| {
-        database::users::create(conn, &username, &email, &password)
-            .map(|user| json!({ "user": user.to_user_auth(&secret) }))
-            .map_err(|error| {
-                let field = match error {
-                    UserCreationError::DuplicatedEmail => "email",
-                    UserCreationError::DuplicatedUsername => "username",
-                };
-                Errors::new(&[(field, "has already been taken")])
-            })
-    })
-    .await
+    let lifetime = state.refresh_token_lifetime;
+    let (user, refresh_token) = db
+        .run(move |conn| -> Result<_, AppError> {
+            let user = database::users::create(conn, &username, &email, &password)?;
+            let refresh_token = database::refresh_tokens::issue(conn, user.id, lifetime)?;
+            Ok((user, refresh_token))
+        })
+        .await?;
+    Ok(json!({ "user": user.to_user_auth(state).with_refresh_token(refresh_token) }))
 }
 
 #[derive(Deserialize)]
@@ -67,7 +64,7 @@ pub async fn post_users_login(
     user: Json<LoginUser>,
     db: Db,
     state: &State<AppState>,
-) -> Result<Value, Errors> {
+) -> Result<Value, AppError> {
     let user = user.into_inner().user;
 
     let mut extractor = FieldValidator::default();
@@ -75,19 +72,74 @@ pub async fn post_users_login(
     let password = extractor.extract("password", user.password);
     extractor.check()?;
 
-    let secret = state.secret.clone();
-    db.run(move |conn| database::users::login(conn, &email, &password))
+    let lifetime = state.refresh_token_lifetime;
+    let (user, refresh_token) = db
+        .run(move |conn| -> Result<_, AppError> {
+            let user = database::users::login(conn, &email, &password)?
+                .ok_or_else(|| Errors::new(&[("email or password", "is invalid")]))?;
+            let refresh_token = database::refresh_tokens::issue(conn, user.id, lifetime)?;
+            Ok((user, refresh_token))
+        })
+        .await?;
+    Ok(json!({ "user": user.to_user_auth(state).with_refresh_token(refresh_token) }))
+}
+
+#[derive(Deserialize)]
+pub struct RefreshToken {
+    user: RefreshTokenData,
+}
+
+#[derive(Deserialize)]
+struct RefreshTokenData {
+    #[serde(rename = "refreshToken")]
+    refresh_token: Option<String>,
+}
+
+/// Exchange a refresh token for a new access and refresh token pair.
+#[post("/users/refresh", format = "json", data = "<token>")]
+pub async fn post_users_refresh(
+    token: Json<RefreshToken>,
+    db: Db,
+    state: &State<AppState>,
+) -> Result<Value, AppError> {
+    let token = token.into_inner().user;
+
+    let mut extractor = FieldValidator::default();
+    let refresh_token = extractor.extract("refreshToken", token.refresh_token);
+    extractor.check()?;
+
+    let lifetime = state.refresh_token_lifetime;
+    let (user, refresh_token) = db
+        .run(move |conn| database::refresh_tokens::rotate(conn, &refresh_token, lifetime))
+        .await?;
+    Ok(json!({ "user": user.to_user_auth(state).with_refresh_token(refresh_token) }))
+}
+
+/// Revoke the refresh token along with every token rotated from it.
+#[post("/users/logout", format = "json", data = "<token>")]
+pub async fn post_users_logout(token: Json<RefreshToken>, db: Db) -> Result<(), AppError> {
+    let token = token.into_inner().user;
+
+    let mut extractor = FieldValidator::default();
+    let refresh_token = extractor.extract("refreshToken", token.refresh_token);
+    extractor.check()?;
+
+    db.run(move |conn| database::refresh_tokens::revoke(conn, &refresh_token))
+        .await
+}
+
+/// Log out everywhere, revoking every access and refresh token of the user.
+#[post("/users/logout/all")]
+pub async fn post_users_logout_all(auth: Auth, db: Db) -> Result<(), AppError> {
+    db.run(move |conn| database::users::revoke_sessions(conn, auth.id))
         .await
-        .map(|user| json!({ "user": user.to_user_auth(&secret) }))
-        .ok_or_else(|| Errors::new(&[("email or password", "is invalid")]))
 }
 
 #[get("/user")]
-pub async fn get_user(auth: Auth, db: Db, state: &State<AppState>) -> Option<Value> {
-    let secret = state.secret.clone();
+pub async fn get_user(auth: Auth, db: Db, state: &State<AppState>) -> Result<Value, AppError> {
     db.run(move |conn| database::users::find(conn, auth.id))
         .await
-        .map(|user| json!({ "user": user.to_user_auth(&secret) }))
+        .map(|user| json!({ "user": user.to_user_auth(state) }))
 }
 
 #[derive(Deserialize)]
@@ -101,9 +153,103 @@ pub async fn put_user(
     auth: Auth,
     db: Db,
     state: &State<AppState>,
-) -> Option<Value> {
-    let secret = state.secret.clone();
-    db.run(move |conn| database::users::update(conn, auth.id, &user.user))
+) -> Result<Value, AppError> {
+    let user = user.into_inner().user;
+    FieldValidator::validate(&user).check()?;
+
+    // Other sessions are revoked with a password change, this one continues
+    // with a new refresh token.
+    let lifetime = state.refresh_token_lifetime;
+    let (user, refresh_token) = db
+        .run(move |conn| -> Result<_, AppError> {
+            let updated = database::users::update(conn, auth.id, &user)?;
+            let refresh_token = if user.changes_password() {
+                Some(database::refresh_tokens::issue(conn, updated.id, lifetime)?)
+            } else {
+                None
+            };
+            Ok((updated, refresh_token))
+        })
+        .await?;
+    let user_auth = user.to_user_auth(state);
+    Ok(match refresh_token {
+        Some(refresh_token) => json!({ "user": user_auth.with_refresh_token(refresh_token) }),
+        None => json!({ "user": user_auth }),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct PasswordReset {
+    user: PasswordResetData,
+}
+
+#[derive(Deserialize)]
+struct PasswordResetData {
+    email: Option<String>,
+}
+
+/// Mail a password reset token to the user.
+///
+/// Succeeds even for unknown emails, so it can't be used to find out
+/// who is registered.
+#[post("/users/password-reset", format = "json", data = "<reset>")]
+pub async fn post_password_reset(
+    reset: Json<PasswordReset>,
+    db: Db,
+    mailer: &State<Box<dyn Mailer>>,
+) -> Result<(), AppError> {
+    let reset = reset.into_inner().user;
+
+    let mut extractor = FieldValidator::default();
+    let email = extractor.extract("email", reset.email);
+    extractor.check()?;
+
+    let to = email.clone();
+    let token = db
+        .run(move |conn| database::password_resets::create(conn, &email))
+        .await?;
+
+    if let Some(token) = token {
+        let mail = Mail {
+            to,
+            subject: "Password reset".to_string(),
+            body: format!(
+                "Use this token to set a new password within an hour:\n{}",
+                token
+            ),
+        };
+        mailer.send(&mail).map_err(|err| {
+            eprintln!("Cannot send password reset mail: {}", err);
+            AppError::Internal
+        })?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirm {
+    user: PasswordResetConfirmData,
+}
+
+#[derive(Deserialize, Validate)]
+struct PasswordResetConfirmData {
+    token: Option<String>,
+    #[validate(length(min = 8))]
+    password: Option<String>,
+}
+
+#[post("/users/password-reset/confirm", format = "json", data = "<confirm>")]
+pub async fn post_password_reset_confirm(
+    confirm: Json<PasswordResetConfirm>,
+    db: Db,
+) -> Result<(), AppError> {
+    let confirm = confirm.into_inner().user;
+
+    let mut extractor = FieldValidator::validate(&confirm);
+    let token = extractor.extract("token", confirm.token);
+    let password = extractor.extract("password", confirm.password);
+    extractor.check()?;
+
+    db.run(move |conn| database::password_resets::confirm(conn, &token, &password))
         .await
-        .map(|user| json!({ "user": user.to_user_auth(&secret) }))
 }
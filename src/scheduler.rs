@@ -0,0 +1,37 @@
+use crate::config::{self, PUBLISH_INTERVAL};
+use crate::database::{self, Db};
+use rocket::fairing::AdHoc;
+use rocket::tokio::time;
+
+/// Periodically publish scheduled articles which are due.
+///
+/// The task keeps one connection of the pool for itself.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Publish scheduled articles", |rocket| {
+        Box::pin(async move {
+            let db = match Db::get_one(rocket).await {
+                Some(db) => db,
+                None => {
+                    eprintln!("Scheduled articles won't be published: no database connection");
+                    return;
+                }
+            };
+            let interval = config::lifetime_from_env("PUBLISH_INTERVAL", PUBLISH_INTERVAL)
+                .to_std()
+                .expect("PUBLISH_INTERVAL should be positive");
+
+            rocket::tokio::spawn(async move {
+                let mut interval = time::interval(interval);
+                loop {
+                    interval.tick().await;
+                    let result = db
+                        .run(|conn| database::articles::publish_scheduled(conn))
+                        .await;
+                    if let Err(err) = result {
+                        eprintln!("Failed to publish scheduled articles: {}", err);
+                    }
+                }
+            });
+        })
+    })
+}
@@ -6,10 +6,36 @@ table! {
         description -> Text,
         body -> Text,
         author -> Int4,
-        tag_list -> Array<Text>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         favorites_count -> Int4,
+        status -> Text,
+        published_at -> Nullable<Timestamptz>,
+        body_html -> Nullable<Text>,
+        word_count -> Int4,
+        reading_time_minutes -> Int4,
+        excerpt -> Text,
+    }
+}
+
+table! {
+    article_revisions (id) {
+        id -> Int4,
+        article -> Int4,
+        number -> Int4,
+        title -> Text,
+        description -> Text,
+        body -> Text,
+        tag_list -> Array<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    article_tags (article, tag) {
+        article -> Int4,
+        tag -> Int4,
+        position -> Int4,
     }
 }
 
@@ -21,20 +47,71 @@ table! {
         author -> Int4,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        body_html -> Nullable<Text>,
+        parent -> Nullable<Int4>,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    follows (follower, followed) {
+        follower -> Int4,
+        followed -> Int4,
     }
 }
 
 table! {
-    favorites (user, article) {
+    password_resets (id) {
+        id -> Int4,
+        user -> Int4,
+        token_hash -> Text,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    reactions (user, article, kind) {
         user -> Int4,
         article -> Int4,
+        kind -> Text,
+        created_at -> Timestamptz,
     }
 }
 
 table! {
-    follows (follower, followed) {
-        follower -> Int4,
-        followed -> Int4,
+    refresh_tokens (id) {
+        id -> Int4,
+        user -> Int4,
+        family -> Text,
+        token_hash -> Text,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    slug_history (slug) {
+        slug -> Text,
+        article -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    tag_follows (user, tag) {
+        user -> Int4,
+        tag -> Int4,
+    }
+}
+
+table! {
+    tags (id) {
+        id -> Int4,
+        name -> Text,
     }
 }
 
@@ -46,13 +123,35 @@ table! {
         bio -> Nullable<Text>,
         image -> Nullable<Text>,
         hash -> Text,
+        token_version -> Int4,
     }
 }
 
+joinable!(article_revisions -> articles (article));
+joinable!(article_tags -> articles (article));
+joinable!(article_tags -> tags (tag));
 joinable!(articles -> users (author));
 joinable!(comments -> articles (article));
 joinable!(comments -> users (author));
-joinable!(favorites -> articles (article));
-joinable!(favorites -> users (user));
+joinable!(password_resets -> users (user));
+joinable!(reactions -> articles (article));
+joinable!(reactions -> users (user));
+joinable!(refresh_tokens -> users (user));
+joinable!(slug_history -> articles (article));
+joinable!(tag_follows -> tags (tag));
+joinable!(tag_follows -> users (user));
 
-allow_tables_to_appear_in_same_query!(articles, comments, favorites, follows, users,);
+allow_tables_to_appear_in_same_query!(
+    article_revisions,
+    article_tags,
+    articles,
+    comments,
+    follows,
+    password_resets,
+    reactions,
+    refresh_tokens,
+    slug_history,
+    tag_follows,
+    tags,
+    users,
+);
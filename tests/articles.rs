@@ -2,8 +2,9 @@
 
 mod common;
 
+use chrono::{Duration, Utc};
 use common::*;
-use rocket::http::{ContentType, Status};
+use rocket::http::{ContentType, Method, Status};
 use rocket::local::blocking::{Client, LocalResponse};
 
 const ARTICLE_TITLE: &str = "Test article";
@@ -48,6 +49,18 @@ fn test_get_article() {
     assert_eq!(body, Some(ARTICLE_BODY));
 }
 
+#[test]
+/// Test that retrieval of an unknown article yields 404 with json body.
+fn test_get_missing_article() {
+    let client = test_client().lock().unwrap();
+    let response = client.get("/api/articles/no-such-article").dispatch();
+
+    assert_eq!(response.status(), Status::NotFound);
+
+    let value = response_json_value(response);
+    value.get("reason").expect("must have a 'reason' field");
+}
+
 #[test]
 /// Test article update.
 fn test_put_articles() {
@@ -79,6 +92,400 @@ fn test_put_articles() {
     assert_eq!(description, Some(new_desc));
 }
 
+#[test]
+/// Test that updates keep revisions which can be diffed and restored.
+fn test_article_revisions() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let slug = article_slug(create_article(&client, token.clone()));
+
+    for body in &["First rewrite", "Second rewrite"] {
+        let response = client
+            .put(format!("/api/articles/{}", slug))
+            .header(ContentType::JSON)
+            .header(token_header(token.clone()))
+            .body(json_string!({"article": {"body": body}}))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let value = response_json_value(
+        client
+            .get(format!("/api/articles/{}/revisions", slug))
+            .dispatch(),
+    );
+    let numbers: Vec<i64> = value
+        .get("revisions")
+        .and_then(|revisions| revisions.as_array())
+        .expect("must have 'revisions' field")
+        .iter()
+        .filter_map(|revision| revision.get("number"))
+        .filter_map(|number| number.as_i64())
+        .collect();
+    assert_eq!(numbers, vec![2, 1]);
+
+    let value = response_json_value(
+        client
+            .get(format!("/api/articles/{}/revisions/1", slug))
+            .dispatch(),
+    );
+    let body = value
+        .get("revision")
+        .and_then(|revision| revision.get("body"))
+        .and_then(|body| body.as_str());
+    assert_eq!(body, Some(ARTICLE_BODY));
+
+    let value = response_json_value(
+        client
+            .get(format!("/api/articles/{}/revisions/1/diff", slug))
+            .dispatch(),
+    );
+    let body = value
+        .get("diff")
+        .and_then(|diff| diff.get("body"))
+        .expect("must have 'body' diff");
+    assert_eq!(
+        body,
+        &serde_json::json!([
+            {"op": "delete", "line": ARTICLE_BODY},
+            {"op": "insert", "line": "Second rewrite"},
+        ])
+    );
+
+    let response = client
+        .get(format!("/api/articles/{}/revisions/9/diff?to=1", slug))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let other = login_as(&client, "intruder", "intruder@realworld.io");
+    let restore_url = format!("/api/articles/{}/revisions/1/restore", slug);
+    let response = client
+        .post(&restore_url)
+        .header(token_header(other))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .post(&restore_url)
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let article = value.get("article").expect("must have 'article' field");
+    assert_eq!(
+        article.get("body").and_then(|x| x.as_str()),
+        Some(ARTICLE_BODY)
+    );
+    assert_eq!(
+        article.get("slug").and_then(|x| x.as_str()),
+        Some(slug.as_str())
+    );
+}
+
+#[test]
+/// Test that old slugs of a renamed article still resolve.
+fn test_renamed_article_old_slug() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let old_slug = article_slug(create_article(&client, token.clone()));
+
+    let response = client
+        .put(format!("/api/articles/{}", old_slug))
+        .header(ContentType::JSON)
+        .header(token_header(token.clone()))
+        .body(json_string!({"article": {"title": "Renamed article"}}))
+        .dispatch();
+    let new_slug = article_slug(response);
+    assert_ne!(new_slug, old_slug);
+
+    let response = client.get(format!("/api/articles/{}", old_slug)).dispatch();
+    assert_eq!(response.status(), Status::MovedPermanently);
+    assert_eq!(
+        response.headers().get_one("Location"),
+        Some(format!("/api/articles/{}", new_slug).as_str())
+    );
+    assert_eq!(article_slug(response), new_slug);
+
+    let response = client.get(format!("/api/articles/{}", new_slug)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .post(format!("/api/articles/{}/favorite", old_slug))
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(article_slug(response), new_slug);
+
+    let comment_id = create_comment(&client, &old_slug, token.clone());
+    let value = response_json_value(
+        client
+            .get(format!("/api/articles/{}/comments", old_slug))
+            .dispatch(),
+    );
+    let ids: Vec<i64> = value
+        .get("comments")
+        .and_then(|comments| comments.as_array())
+        .expect("must have 'comments' field")
+        .iter()
+        .filter_map(|comment| comment.get("id"))
+        .filter_map(|id| id.as_i64())
+        .collect();
+    assert_eq!(ids, vec![comment_id]);
+
+    let response = client
+        .post(format!("/api/articles/{}/revisions/1/restore", old_slug))
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_ne!(article_slug(response), new_slug);
+
+    let response = client
+        .put(format!("/api/articles/{}", new_slug))
+        .header(ContentType::JSON)
+        .header(token_header(token.clone()))
+        .body(json_string!({"article": {"body": "Edited by an old slug"}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .delete(format!("/api/articles/{}", new_slug))
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get(format!("/api/articles/{}", old_slug)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+/// Test that articles with the same title get counted slugs.
+fn test_article_slug_counter() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let first = article_slug(create_article(&client, token.clone()));
+    let second = article_slug(create_article(&client, token.clone()));
+    assert_ne!(first, second);
+    let counter = second
+        .strip_prefix("test-article-")
+        .expect("must have a counter suffix");
+    assert!(counter.parse::<u32>().is_ok());
+
+    let response = post_article(
+        &client,
+        token,
+        json_string!({
+            "article": {
+                "title": "?!",
+                "description": "Nothing to slugify",
+                "body": ARTICLE_BODY,
+                "tagList": []
+            }
+        }),
+    );
+    assert_eq!(response.status(), Status::Ok);
+    assert!(article_slug(response).starts_with("article"));
+}
+
+#[test]
+/// Test that titles matching a static route still get a reachable slug.
+fn test_article_slug_reserved() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    for title in &["Feed", "Search"] {
+        let response = post_article(
+            &client,
+            token.clone(),
+            json_string!({
+                "article": {
+                    "title": title,
+                    "description": "Named like a route",
+                    "body": ARTICLE_BODY,
+                    "tagList": []
+                }
+            }),
+        );
+        assert_eq!(response.status(), Status::Ok);
+        let slug = article_slug(response);
+        assert_ne!(slug, title.to_lowercase());
+
+        let response = client.get(format!("/api/articles/{}", slug)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(article_slug(response), slug);
+    }
+}
+
+#[test]
+/// Test rendering markdown bodies to sanitized html on request.
+fn test_article_body_html() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let response = post_article(
+        &client,
+        token.clone(),
+        json_string!({
+            "article": {
+                "title": ARTICLE_TITLE,
+                "description": "Markdown",
+                "body": "*Hello* <script>alert(1)</script>",
+                "tagList": []
+            }
+        }),
+    );
+    let value = response_json_value(response);
+    let article = value.get("article").expect("must have 'article' field");
+    assert!(article.get("bodyHtml").is_none());
+    let slug = article.get("slug").and_then(|slug| slug.as_str()).unwrap();
+
+    let response = client.get(format!("/api/articles/{}", slug)).dispatch();
+    let value = response_json_value(response);
+    assert!(value.get("article").unwrap().get("bodyHtml").is_none());
+
+    let response = client
+        .get(format!("/api/articles/{}?html=true", slug))
+        .dispatch();
+    let value = response_json_value(response);
+    let html = value
+        .get("article")
+        .and_then(|article| article.get("bodyHtml"))
+        .and_then(|html| html.as_str())
+        .expect("must have 'bodyHtml' field");
+    assert!(html.contains("<em>Hello</em>"));
+    assert!(!html.contains("script"));
+
+    let value = response_json_value(
+        client
+            .get(format!(
+                "/api/articles?author={}&limit=1&html=true",
+                USERNAME
+            ))
+            .dispatch(),
+    );
+    assert_eq!(articles_field(&value, "bodyHtml").len(), 1);
+
+    let response = client
+        .post(format!("/api/articles/{}/comments", slug))
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"comment": {"body": "**Bold**"}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(
+        client
+            .get(format!("/api/articles/{}/comments?html=true", slug))
+            .dispatch(),
+    );
+    let html = value
+        .get("comments")
+        .and_then(|comments| comments.get(0))
+        .and_then(|comment| comment.get("bodyHtml"))
+        .and_then(|html| html.as_str());
+    assert_eq!(html, Some("<p><strong>Bold</strong></p>\n"));
+}
+
+#[test]
+/// Test word count, reading time and excerpt computed on write.
+fn test_article_reading_stats() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let body = format!("# Heading\n\n{}", "word ".repeat(449));
+    let response = post_article(
+        &client,
+        token.clone(),
+        json_string!({
+            "article": {
+                "title": ARTICLE_TITLE,
+                "body": body,
+                "tagList": []
+            }
+        }),
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let article = value.get("article").expect("must have 'article' field");
+    assert_eq!(article.get("wordCount").and_then(|x| x.as_i64()), Some(450));
+    assert_eq!(
+        article.get("readingTimeMinutes").and_then(|x| x.as_i64()),
+        Some(3)
+    );
+    let excerpt = article.get("excerpt").and_then(|x| x.as_str()).unwrap();
+    assert!(excerpt.starts_with("Heading word word"));
+    assert!(excerpt.ends_with('…'));
+    let slug = article.get("slug").and_then(|x| x.as_str()).unwrap();
+
+    let response = client
+        .put(format!("/api/articles/{}", slug))
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"article": {"description": "Now described"}}))
+        .dispatch();
+    let value = response_json_value(response);
+    let article = value.get("article").expect("must have 'article' field");
+    assert_eq!(
+        article.get("excerpt").and_then(|x| x.as_str()),
+        Some("Now described")
+    );
+    assert_eq!(article.get("wordCount").and_then(|x| x.as_i64()), Some(450));
+}
+
+#[test]
+/// Test that only the author can update an article.
+fn test_put_articles_by_another_user() {
+    let client = test_client().lock().unwrap();
+    let response = create_article(&client, login(&client));
+
+    let slug = article_slug(response);
+
+    let token = login_as(&client, "intruder", "intruder@realworld.io");
+    let response = client
+        .put(format!("/api/articles/{}", slug))
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"article": {"description": "Hijacked"}}))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[test]
+/// Test updating an article which doesn't exist.
+fn test_put_missing_article() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+
+    let response = client
+        .put("/api/articles/no-such-article")
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"article": {"description": "Nothing here"}}))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+/// Test that article update is validated.
+fn test_put_articles_with_empty_title() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let response = create_article(&client, token.clone());
+
+    let slug = article_slug(response);
+
+    let response = client
+        .put(format!("/api/articles/{}", slug))
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"article": {"title": ""}}))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let value = response_json_value(response);
+    value
+        .get("errors")
+        .and_then(|errors| errors.get("title"))
+        .expect("must have 'title' errors");
+}
+
 #[test]
 /// Test article deletion.
 fn test_delete_article() {
@@ -144,6 +551,83 @@ fn test_unfavorite_article() {
     assert_eq!(response.status(), Status::Ok);
 }
 
+#[test]
+/// Test that favoriting twice or unfavoriting twice keeps the count right.
+fn test_favorite_idempotent() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let slug = article_slug(create_article(&client, token.clone()));
+    realworld::recount_favorites().expect("cannot recount favorites");
+
+    let favorites_count = |method: Method| {
+        let response = client
+            .req(method, format!("/api/articles/{}/favorite", slug))
+            .header(token_header(token.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response_json_value(response)["article"]["favoritesCount"].as_i64()
+    };
+
+    assert_eq!(favorites_count(Method::Post), Some(1));
+    assert_eq!(favorites_count(Method::Post), Some(1));
+    assert_eq!(favorites_count(Method::Delete), Some(0));
+    assert_eq!(favorites_count(Method::Delete), Some(0));
+    assert_eq!(favorites_count(Method::Post), Some(1));
+
+    // Nothing left to fix, counters are kept right.
+    assert_eq!(realworld::recount_favorites().ok(), Some(0));
+}
+
+#[test]
+/// Test reacting to articles, favorites being likes.
+fn test_reactions() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let slug = article_slug(create_article(&client, token.clone()));
+    let reader_token = login_as(&client, "reactor", "reactor@realworld.io");
+
+    let request = |method: Method, path: &str, token: Token| {
+        let response = client
+            .req(method, format!("/api/articles/{}/{}", slug, path))
+            .header(token_header(token))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response_json_value(response)["article"].clone()
+    };
+
+    request(Method::Post, "reactions/clap", token.clone());
+    let first = request(Method::Post, "reactions/clap", reader_token.clone());
+    let again = request(Method::Post, "reactions/clap", reader_token.clone());
+    assert_eq!(first, again, "reacting twice must change nothing");
+    assert_eq!(again["reactions"]["clap"].as_i64(), Some(2));
+    assert_eq!(again["reactions"]["heart"].as_i64(), Some(0));
+    assert_eq!(again["myReactions"], serde_json::json!(["clap"]));
+
+    let article = request(Method::Post, "favorite", reader_token.clone());
+    assert_eq!(article["favorited"].as_bool(), Some(true));
+    assert_eq!(article["favoritesCount"].as_i64(), Some(1));
+    assert_eq!(article["reactions"]["like"].as_i64(), Some(1));
+    assert_eq!(article["myReactions"], serde_json::json!(["like", "clap"]));
+
+    let article = request(Method::Delete, "reactions/like", reader_token.clone());
+    assert_eq!(article["favorited"].as_bool(), Some(false));
+    assert_eq!(article["favoritesCount"].as_i64(), Some(0));
+    let article = request(Method::Delete, "reactions/clap", reader_token.clone());
+    assert_eq!(article["reactions"]["clap"].as_i64(), Some(1));
+    assert_eq!(article["myReactions"], serde_json::json!([]));
+
+    let response = client.get(format!("/api/articles/{}", slug)).dispatch();
+    let article = &response_json_value(response)["article"];
+    assert_eq!(article["reactions"]["clap"].as_i64(), Some(1));
+    assert_eq!(article["myReactions"], serde_json::json!([]));
+
+    let response = client
+        .post(format!("/api/articles/{}/reactions/meh", slug))
+        .header(token_header(reader_token))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
 #[test]
 /// Test getting multiple articles.
 fn test_get_articles() {
@@ -184,74 +668,960 @@ fn test_get_articles_with_params() {
 }
 
 #[test]
-/// Test getting articles feed.
-fn test_get_articles_fedd() {
+/// Test that author and tag filters are combined with AND.
+fn test_get_articles_by_author_and_tag() {
     let client = test_client().lock().unwrap();
-    let token = login(&client);
+    create_article(&client, login(&client));
+    let token = login_as(&client, "comboauthor", "comboauthor@realworld.io");
+    create_article_with_tags(&client, token, &["combo"]);
 
-    let url = "/api/articles/feed?limit=1&offset=0";
-    let response = client.get(url).header(token_header(token)).dispatch();
+    let value = response_json_value(
+        client
+            .get("/api/articles?author=comboauthor&tag=foo&limit=100")
+            .dispatch(),
+    );
+    assert!(articles_authors(&value)
+        .iter()
+        .all(|author| author == "comboauthor"));
+    assert!(articles_tags(&value)
+        .iter()
+        .all(|tags| tags.contains(&"foo".to_string())));
+    assert_eq!(
+        value.get("articlesCount").and_then(|count| count.as_i64()),
+        Some(0)
+    );
+}
 
-    assert_eq!(response.status(), Status::Ok);
+#[test]
+/// Test combining multiple tags with `tagMode`.
+fn test_get_articles_by_multiple_tags() {
+    let client = test_client().lock().unwrap();
+    let token = login_as(&client, "tagger", "tagger@realworld.io");
+    create_article_with_tags(&client, token.clone(), &["alpha", "beta"]);
+    create_article_with_tags(&client, token, &["alpha"]);
 
-    let value = response_json_value(response);
-    value.get("articles").expect("must have 'articles' field");
+    let url = "/api/articles?author=tagger&tag=alpha&tag=beta&tagMode=all&limit=100";
+    let value = response_json_value(client.get(url).dispatch());
+    let tags = articles_tags(&value);
+    assert!(!tags.is_empty());
+    assert!(tags
+        .iter()
+        .all(|tags| tags.contains(&"alpha".to_string()) && tags.contains(&"beta".to_string())));
+
+    let url = "/api/articles?author=tagger&tag=alpha&tag=beta&tagMode=any&limit=100";
+    let value = response_json_value(client.get(url).dispatch());
+    let tags = articles_tags(&value);
+    assert!(tags.iter().any(|tags| !tags.contains(&"beta".to_string())));
 }
 
 #[test]
-/// Test posting and deleteing of comments.
-fn test_commenting() {
+/// Test that updated tags replace the old ones, in order, for reads and filters.
+fn test_put_article_tags() {
     let client = test_client().lock().unwrap();
-    let token = login(&client);
-    let response = create_article(&client, token.clone());
-
-    let slug = article_slug(response);
+    let token = login_as(&client, "retagger", "retagger@realworld.io");
+    let slug = article_slug(create_article_with_tags(
+        &client,
+        token.clone(),
+        &["zeta", "old"],
+    ));
 
     let response = client
-        .post(format!("/api/articles/{}/comments", slug))
+        .put(format!("/api/articles/{}", slug))
         .header(ContentType::JSON)
-        .header(token_header(token.clone()))
-        .body(json_string!({
-            "comment": {
-                "body": "Like!",
-            }
-        }))
+        .header(token_header(token))
+        .body(json_string!({"article": {"tagList": ["zulu", "alpha", "zeta"]}}))
         .dispatch();
+    let value = response_json_value(response);
+    assert_eq!(
+        value["article"]["tagList"],
+        serde_json::json!(["zulu", "alpha", "zeta"])
+    );
 
-    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(client.get(format!("/api/articles/{}", slug)).dispatch());
+    assert_eq!(
+        value["article"]["tagList"],
+        serde_json::json!(["zulu", "alpha", "zeta"])
+    );
+    for (url, found) in &[
+        ("/api/articles?author=retagger&tag=old", false),
+        ("/api/articles?author=retagger&tag=zulu", true),
+        ("/api/articles?author=retagger&tag=old&tag=zulu", true),
+        (
+            "/api/articles?author=retagger&tag=zulu&tag=zeta&tagMode=all",
+            true,
+        ),
+        (
+            "/api/articles?author=retagger&tag=zulu&tag=old&tagMode=all",
+            false,
+        ),
+    ] {
+        let value = response_json_value(client.get(*url).dispatch());
+        assert_eq!(article_slugs(&value).contains(&slug), *found, "{}", url);
+    }
+}
 
-    let value = response_json_value(response);
-    let comment_id = value
-        .get("comment")
-        .and_then(|comment| comment.get("id"))
-        .and_then(|id| id.as_i64())
-        .expect("must have comment 'id' field");
+#[test]
+/// Test that tag filters are normalized like the tags themselves.
+fn test_get_articles_by_mixed_case_tag() {
+    let client = test_client().lock().unwrap();
+    let token = login_as(&client, "tagger", "tagger@realworld.io");
+    let slug = article_slug(create_article_with_tags(&client, token, &["MixedCase"]));
+
+    for url in &[
+        "/api/articles?author=tagger&tag=MixedCase&limit=100",
+        "/api/articles?author=tagger&tag=%20MIXEDCASE%20&tagMode=all&limit=100",
+    ] {
+        let value = response_json_value(client.get(*url).dispatch());
+        assert!(article_slugs(&value).contains(&slug), "{}", url);
+    }
+}
 
+#[test]
+/// Test combining favorited and author filters.
+fn test_get_articles_favorited_by_author() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let author_token = login_as(&client, "favauthor", "favauthor@realworld.io");
+    let slug = article_slug(create_article(&client, author_token));
+    create_article(&client, token.clone());
     let response = client
-        .delete(format!("/api/articles/{}/comments/{}", slug, comment_id))
-        .header(token_header(token))
+        .post(format!("/api/articles/{}/favorite", slug))
+        .header(token_header(token.clone()))
         .dispatch();
-
     assert_eq!(response.status(), Status::Ok);
+
+    let url = format!(
+        "/api/articles?favorited={}&author=favauthor&limit=100",
+        USERNAME
+    );
+    let response = client.get(url).header(token_header(token)).dispatch();
+    let value = response_json_value(response);
+    assert!(article_slugs(&value).contains(&slug));
+    assert!(articles_authors(&value)
+        .iter()
+        .all(|author| author == "favauthor"));
+    assert!(value
+        .get("articles")
+        .and_then(|articles| articles.as_array())
+        .expect("must have 'articles' field")
+        .iter()
+        .all(|article| article.get("favorited") == Some(&serde_json::Value::Bool(true))));
 }
 
 #[test]
-/// Test getting comments.
-fn test_get_comment() {
+/// Test full-text search with highlighted snippets.
+fn test_search_articles() {
     let client = test_client().lock().unwrap();
     let token = login(&client);
-    let response = create_article(&client, token.clone());
-
-    let slug = article_slug(response);
+    create_article(&client, token.clone());
 
     let response = client
-        .get(format!("/api/articles/{}/comments", slug))
+        .get("/api/articles/search?q=obviously&limit=1")
+        .header(token_header(token))
         .dispatch();
-
     assert_eq!(response.status(), Status::Ok);
-
     let value = response_json_value(response);
-    let comments_num = value
+    let article = value
+        .get("articles")
+        .and_then(|articles| articles.get(0))
+        .expect("must find an article");
+    let snippet = article
+        .get("snippet")
+        .and_then(|snippet| snippet.as_str())
+        .expect("must have a snippet");
+    assert!(snippet.contains("<mark>obviously</mark>"));
+    assert!(article.get("favorited").is_some());
+    assert!(value.get("articlesCount").and_then(|count| count.as_i64()) > Some(0));
+
+    let value = response_json_value(client.get("/api/articles/search?q=xyzzyplugh").dispatch());
+    assert_eq!(
+        value.get("articlesCount").and_then(|count| count.as_i64()),
+        Some(0)
+    );
+}
+
+#[test]
+/// Test that markup of the body is escaped in snippets, only highlights remain.
+fn test_search_snippet_escaped() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let response = post_article(
+        &client,
+        token,
+        json_string!({
+            "article": {
+                "title": "Escaping",
+                "description": "",
+                "body": "<script>alert(1)</script> quuxescape <img src=x onerror=alert(1)>",
+                "tagList": []
+            }
+        }),
+    );
+    assert_eq!(response.status(), Status::Ok);
+
+    let value = response_json_value(client.get("/api/articles/search?q=quuxescape").dispatch());
+    let snippet = value["articles"][0]["snippet"]
+        .as_str()
+        .expect("must have a snippet");
+    assert!(snippet.contains("<mark>quuxescape</mark>"), "{}", snippet);
+    assert!(!snippet.contains("<script"), "{}", snippet);
+    assert!(!snippet.contains("<img"), "{}", snippet);
+    assert!(snippet.contains("&lt;img"), "{}", snippet);
+}
+
+#[test]
+/// Test that search requires terms.
+fn test_search_articles_without_terms() {
+    let client = test_client().lock().unwrap();
+    let response = client.get("/api/articles/search?q=%20").dispatch();
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let value = response_json_value(response);
+    value
+        .get("errors")
+        .and_then(|errors| errors.get("q"))
+        .expect("must have 'q' errors");
+}
+
+#[test]
+/// Test that drafts are visible only to their author until published.
+fn test_draft_article() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let response = post_article(
+        &client,
+        token.clone(),
+        json_string!({
+            "article": {
+                "title": ARTICLE_TITLE,
+                "description": "Not ready yet",
+                "body": ARTICLE_BODY,
+                "tagList": [],
+                "status": "draft"
+            }
+        }),
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let article = value.get("article").expect("must have 'article' field");
+    assert_eq!(
+        article.get("status").and_then(|x| x.as_str()),
+        Some("draft")
+    );
+    assert_eq!(article.get("publishedAt"), Some(&serde_json::Value::Null));
+    let slug = article
+        .get("slug")
+        .and_then(|slug| slug.as_str())
+        .map(String::from)
+        .expect("must have article 'slug'");
+
+    let url = format!("/api/articles/{}", slug);
+    assert_eq!(client.get(&url).dispatch().status(), Status::NotFound);
+    let response = client
+        .get(&url)
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let list_url = format!("/api/articles?author={}&limit=100", USERNAME);
+    let value = response_json_value(client.get(&list_url).dispatch());
+    assert!(!article_slugs(&value).contains(&slug));
+    let response = client
+        .get(&list_url)
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert!(article_slugs(&response_json_value(response)).contains(&slug));
+
+    // Other users can't tell the draft exists.
+    let other = login_as(&client, "smith", "smith@realworld.io");
+    let publish_url = format!("/api/articles/{}/publish", slug);
+    let response = client
+        .post(&publish_url)
+        .header(token_header(other.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client
+        .put(&url)
+        .header(ContentType::JSON)
+        .header(token_header(other.clone()))
+        .body(json_string!({"article": {"body": "Hijacked"}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.delete(&url).header(token_header(other)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client
+        .post(&publish_url)
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let article = value.get("article").expect("must have 'article' field");
+    assert_eq!(
+        article.get("status").and_then(|x| x.as_str()),
+        Some("published")
+    );
+    assert!(article
+        .get("publishedAt")
+        .and_then(|x| x.as_str())
+        .is_some());
+    assert_eq!(client.get(&url).dispatch().status(), Status::Ok);
+}
+
+#[test]
+/// Test that a draft published later is listed before older articles.
+fn test_published_draft_listed_first() {
+    let client = test_client().lock().unwrap();
+    let token = login_as(&client, "latepublisher", "latepublisher@realworld.io");
+    let response = post_article(
+        &client,
+        token.clone(),
+        json_string!({
+            "article": {
+                "title": ARTICLE_TITLE,
+                "description": "Written first, published last",
+                "body": ARTICLE_BODY,
+                "tagList": [],
+                "status": "draft"
+            }
+        }),
+    );
+    let draft = article_slug(response);
+    let published = article_slug(create_article(&client, token.clone()));
+
+    let response = client
+        .post(format!("/api/articles/{}/publish", draft))
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let value = response_json_value(client.get("/api/articles?author=latepublisher").dispatch());
+    assert_eq!(article_slugs(&value)[..2], [draft, published]);
+}
+
+#[test]
+/// Test scheduling an article for later.
+fn test_scheduled_article() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let publish_at = (Utc::now() + Duration::hours(1)).to_rfc3339();
+    let response = post_article(
+        &client,
+        token.clone(),
+        json_string!({
+            "article": {
+                "title": ARTICLE_TITLE,
+                "description": "Coming soon",
+                "body": ARTICLE_BODY,
+                "tagList": [],
+                "publishAt": publish_at
+            }
+        }),
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let article = value.get("article").expect("must have 'article' field");
+    assert_eq!(
+        article.get("status").and_then(|x| x.as_str()),
+        Some("scheduled")
+    );
+    let slug = article.get("slug").and_then(|slug| slug.as_str()).unwrap();
+    let response = client.get(format!("/api/articles/{}", slug)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let publish_at = (Utc::now() - Duration::hours(1)).to_rfc3339();
+    let response = post_article(
+        &client,
+        token,
+        json_string!({
+            "article": {
+                "title": ARTICLE_TITLE,
+                "description": "Too late",
+                "body": ARTICLE_BODY,
+                "tagList": [],
+                "status": "scheduled",
+                "publishAt": publish_at
+            }
+        }),
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let value = response_json_value(response);
+    value
+        .get("errors")
+        .and_then(|errors| errors.get("publishAt"))
+        .expect("must have 'publishAt' errors");
+}
+
+#[test]
+/// Test walking through articles with cursors.
+fn test_get_articles_with_cursor() {
+    let client = test_client().lock().unwrap();
+    let token = login_as(&client, "pager", "pager@realworld.io");
+    for _ in 0..3 {
+        create_article(&client, token.clone());
+    }
+
+    let first_page =
+        response_json_value(client.get("/api/articles?author=pager&limit=2").dispatch());
+    let next_cursor = first_page
+        .get("nextCursor")
+        .and_then(|cursor| cursor.as_str())
+        .expect("must have 'nextCursor' field");
+
+    let second_page = response_json_value(
+        client
+            .get(format!(
+                "/api/articles?author=pager&limit=2&cursor={}",
+                next_cursor
+            ))
+            .dispatch(),
+    );
+    let first_slugs = article_slugs(&first_page);
+    let second_slugs = article_slugs(&second_page);
+    assert!(!second_slugs.is_empty());
+    assert!(second_slugs.iter().all(|slug| !first_slugs.contains(slug)));
+    let count =
+        |page: &serde_json::Value| page.get("articlesCount").and_then(|count| count.as_i64());
+    assert!(count(&first_page) >= Some(3));
+    assert_eq!(
+        count(&second_page),
+        count(&first_page),
+        "total on every page"
+    );
+
+    let dates = article_dates(&first_page)
+        .into_iter()
+        .chain(article_dates(&second_page))
+        .collect::<Vec<_>>();
+    assert!(
+        dates.windows(2).all(|pair| pair[0] >= pair[1]),
+        "newest first"
+    );
+
+    let prev_cursor = second_page
+        .get("prevCursor")
+        .and_then(|cursor| cursor.as_str())
+        .expect("must have 'prevCursor' field");
+    let previous_page = response_json_value(
+        client
+            .get(format!(
+                "/api/articles?author=pager&limit=2&before={}",
+                prev_cursor
+            ))
+            .dispatch(),
+    );
+    assert_eq!(article_slugs(&previous_page), first_slugs);
+}
+
+#[test]
+/// Test that a malformed cursor is rejected.
+fn test_get_articles_with_invalid_cursor() {
+    let client = test_client().lock().unwrap();
+    let response = client.get("/api/articles?cursor=garbage").dispatch();
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+/// Test that editing an article moves it to the top of `sort=updated`.
+fn test_get_articles_sorted_by_updated() {
+    let client = test_client().lock().unwrap();
+    let token = login_as(&client, "updater", "updater@realworld.io");
+    let older = article_slug(create_article(&client, token.clone()));
+    let newer = article_slug(create_article(&client, token.clone()));
+
+    let url = "/api/articles?author=updater&sort=updated&limit=1";
+    let value = response_json_value(client.get(url).dispatch());
+    assert_eq!(article_slugs(&value), vec![newer]);
+
+    let response = client
+        .put(format!("/api/articles/{}", older))
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"article": {"body": "Freshly edited"}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let article = &value["article"];
+    assert!(article["updatedAt"].as_str() > article["createdAt"].as_str());
+
+    let value = response_json_value(client.get(url).dispatch());
+    assert_eq!(article_slugs(&value), vec![older]);
+}
+
+#[test]
+/// Test ordering articles by favorites and paginating in that order.
+fn test_get_articles_sorted_by_favorites() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let slug = article_slug(create_article(&client, token.clone()));
+    let response = client
+        .post(format!("/api/articles/{}/favorite", slug))
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let first_page = response_json_value(
+        client
+            .get("/api/articles?sort=favorites&limit=2")
+            .dispatch(),
+    );
+    let next_cursor = first_page
+        .get("nextCursor")
+        .and_then(|cursor| cursor.as_str())
+        .expect("must have 'nextCursor' field");
+    let second_page = response_json_value(
+        client
+            .get(format!(
+                "/api/articles?sort=favorites&limit=2&cursor={}",
+                next_cursor
+            ))
+            .dispatch(),
+    );
+
+    let counts = articles_counts(&first_page)
+        .into_iter()
+        .chain(articles_counts(&second_page))
+        .collect::<Vec<_>>();
+    assert!(counts[0] >= 1);
+    assert!(
+        counts.windows(2).all(|pair| pair[0] >= pair[1]),
+        "most favorited first"
+    );
+
+    // Cursor made for another order can't be used.
+    let response = client
+        .get(format!("/api/articles?sort=created&cursor={}", next_cursor))
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+/// Test that feed reports total number of articles, not the page size.
+fn test_get_articles_feed_count() {
+    let client = test_client().lock().unwrap();
+    let author_token = login_as(&client, "feedauthor", "feedauthor@realworld.io");
+    create_article(&client, author_token.clone());
+    create_article(&client, author_token);
+
+    let token = login_as(&client, "feeder", "feeder@realworld.io");
+    let response = client
+        .post("/api/profiles/feedauthor/follow")
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .get("/api/articles/feed?limit=1")
+        .header(token_header(token.clone()))
+        .dispatch();
+    let value = response_json_value(response);
+    let count = value
+        .get("articlesCount")
+        .and_then(|count| count.as_i64())
+        .expect("must have 'articlesCount' field");
+    assert_eq!(article_slugs(&value).len(), 1);
+    assert!(count >= 2);
+
+    let response = client
+        .delete("/api/profiles/feedauthor/follow")
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+/// Test that embedded authors are profiles with `following` as seen by the viewer.
+fn test_author_profiles() {
+    let client = test_client().lock().unwrap();
+    let author_token = login_as(&client, "profileauthor", "profileauthor@realworld.io");
+    let slug = article_slug(create_article(&client, author_token.clone()));
+    create_comment(&client, &slug, author_token);
+
+    let token = login_as(&client, "profilereader", "profilereader@realworld.io");
+    let response = client
+        .post("/api/profiles/profileauthor/follow")
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let authors = |token: Option<Token>| {
+        let get = |url: String| {
+            let mut request = client.get(url);
+            if let Some(token) = token.clone() {
+                request = request.header(token_header(token));
+            }
+            response_json_value(request.dispatch())
+        };
+        vec![
+            get(format!("/api/articles/{}", slug))["article"]["author"].clone(),
+            get("/api/articles?author=profileauthor".to_string())["articles"][0]["author"].clone(),
+            get(format!("/api/articles/{}/comments", slug))["comments"][0]["author"].clone(),
+        ]
+    };
+
+    for author in authors(Some(token.clone())) {
+        assert_eq!(author["username"].as_str(), Some("profileauthor"));
+        assert_eq!(author["following"].as_bool(), Some(true));
+        assert!(author.get("email").is_none());
+        assert!(author.get("id").is_none());
+    }
+    for author in authors(None) {
+        assert_eq!(author["following"].as_bool(), Some(false));
+    }
+
+    let response = client
+        .delete("/api/profiles/profileauthor/follow")
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+/// Test getting articles feed.
+fn test_get_articles_fedd() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+
+    let url = "/api/articles/feed?limit=1&offset=0";
+    let response = client.get(url).header(token_header(token)).dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let value = response_json_value(response);
+    value.get("articles").expect("must have 'articles' field");
+}
+
+#[test]
+/// Test posting and deleteing of comments.
+fn test_commenting() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let response = create_article(&client, token.clone());
+
+    let slug = article_slug(response);
+
+    let response = client
+        .post(format!("/api/articles/{}/comments", slug))
+        .header(ContentType::JSON)
+        .header(token_header(token.clone()))
+        .body(json_string!({
+            "comment": {
+                "body": "Like!",
+            }
+        }))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let value = response_json_value(response);
+    let comment_id = value
+        .get("comment")
+        .and_then(|comment| comment.get("id"))
+        .and_then(|id| id.as_i64())
+        .expect("must have comment 'id' field");
+
+    let response = client
+        .delete(format!("/api/articles/{}/comments/{}", slug, comment_id))
+        .header(token_header(token))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+/// Test that a comment can be deleted only by its author or the article author.
+fn test_delete_comment_permissions() {
+    let client = test_client().lock().unwrap();
+    let author_token = login(&client);
+    let response = create_article(&client, author_token.clone());
+    let slug = article_slug(response);
+
+    let commenter_token = login_as(&client, "commenter", "commenter@realworld.io");
+    let intruder_token = login_as(&client, "intruder", "intruder@realworld.io");
+
+    let comment_id = create_comment(&client, &slug, commenter_token.clone());
+    let response = client
+        .delete(format!("/api/articles/{}/comments/{}", slug, comment_id))
+        .header(token_header(intruder_token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .delete(format!("/api/articles/{}/comments/{}", slug, comment_id))
+        .header(token_header(author_token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let comment_id = create_comment(&client, &slug, commenter_token.clone());
+    let response = client
+        .delete(format!("/api/articles/{}/comments/{}", slug, comment_id))
+        .header(token_header(commenter_token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+/// Test that a comment can be edited only by its author and gets marked as edited.
+fn test_edit_comment() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let slug = article_slug(create_article(&client, token.clone()));
+    let commenter_token = login_as(&client, "commenter", "commenter@realworld.io");
+
+    let comment_id = create_comment(&client, &slug, commenter_token.clone());
+    assert_eq!(
+        get_comments(&client, &slug)[0]["edited"].as_bool(),
+        Some(false)
+    );
+
+    let edit = |token: Token, body: &str| {
+        client
+            .put(format!("/api/articles/{}/comments/{}", slug, comment_id))
+            .header(ContentType::JSON)
+            .header(token_header(token))
+            .body(json_string!({"comment": {"body": body}}))
+            .dispatch()
+    };
+
+    assert_eq!(edit(token, "Hijacked").status(), Status::Forbidden);
+    assert_eq!(
+        edit(commenter_token.clone(), "").status(),
+        Status::UnprocessableEntity
+    );
+
+    let response = edit(commenter_token, "Like it even more!");
+    assert_eq!(response.status(), Status::Ok);
+    let comment = &response_json_value(response)["comment"];
+    assert_eq!(comment["id"].as_i64(), Some(comment_id));
+    assert_eq!(comment["body"].as_str(), Some("Like it even more!"));
+    assert_eq!(comment["edited"].as_bool(), Some(true));
+
+    let comments = get_comments(&client, &slug);
+    assert_eq!(comments[0]["body"].as_str(), Some("Like it even more!"));
+    assert_eq!(comments[0]["edited"].as_bool(), Some(true));
+}
+
+#[test]
+/// Test that a comment can't be deleted through another article.
+fn test_delete_comment_of_another_article() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let slug = article_slug(create_article(&client, token.clone()));
+    let other_slug = article_slug(create_article(&client, token.clone()));
+
+    let comment_id = create_comment(&client, &slug, token.clone());
+    let response = client
+        .delete(format!(
+            "/api/articles/{}/comments/{}",
+            other_slug, comment_id
+        ))
+        .header(token_header(token))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+/// Test commenting an article which doesn't exist.
+fn test_comment_missing_article() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+
+    let response = client
+        .post("/api/articles/no-such-article/comments")
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"comment": {"body": "Hello?"}}))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+/// Test replying to comments, thread ordering and deletion of a parent comment.
+fn test_comment_threads() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let slug = article_slug(create_article(&client, token.clone()));
+    let other_slug = article_slug(create_article(&client, token.clone()));
+
+    let first = create_comment(&client, &slug, token.clone());
+    let second = create_comment(&client, &slug, token.clone());
+    let reply = reply_to_comment(&client, &slug, first, token.clone());
+    let nested = reply_to_comment(&client, &slug, reply, token.clone());
+
+    let comments = get_comments(&client, &slug);
+    let thread: Vec<_> = comments
+        .iter()
+        .map(|comment| {
+            (
+                comment["id"].as_i64().unwrap(),
+                comment["parentId"].as_i64(),
+                comment["depth"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        thread,
+        vec![
+            (first, None, 0),
+            (reply, Some(first), 1),
+            (nested, Some(reply), 2),
+            (second, None, 0),
+        ]
+    );
+
+    let response = client
+        .post(format!("/api/articles/{}/comments", other_slug))
+        .header(ContentType::JSON)
+        .header(token_header(token.clone()))
+        .body(json_string!({"comment": {"body": "Wrong thread", "parentId": first}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = client
+        .delete(format!("/api/articles/{}/comments/{}", slug, first))
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let comments = get_comments(&client, &slug);
+    assert_eq!(comments.len(), 4);
+    assert_eq!(comments[0]["id"].as_i64(), Some(first));
+    assert_eq!(comments[0]["deleted"].as_bool(), Some(true));
+    assert_eq!(comments[0]["body"].as_str(), Some("[deleted]"));
+    assert_eq!(comments[0]["author"], serde_json::Value::Null);
+    assert_eq!(comments[1]["id"].as_i64(), Some(reply));
+    assert_eq!(comments[1]["deleted"].as_bool(), Some(false));
+
+    let response = client
+        .post(format!("/api/articles/{}/comments", slug))
+        .header(ContentType::JSON)
+        .header(token_header(token.clone()))
+        .body(json_string!({"comment": {"body": "Too late", "parentId": first}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = client
+        .delete(format!("/api/articles/{}/comments/{}", slug, second))
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(get_comments(&client, &slug).len(), 3);
+
+    // Removing the last reply takes the tombstones above it along.
+    for id in &[reply, nested] {
+        let response = client
+            .delete(format!("/api/articles/{}/comments/{}", slug, id))
+            .header(token_header(token.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+    assert!(get_comments(&client, &slug).is_empty());
+}
+
+#[test]
+/// Test that comments of drafts are visible only to their author.
+fn test_draft_comments() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let response = post_article(
+        &client,
+        token.clone(),
+        json_string!({
+            "article": {
+                "title": ARTICLE_TITLE,
+                "description": "Not ready yet",
+                "body": ARTICLE_BODY,
+                "tagList": [],
+                "status": "draft"
+            }
+        }),
+    );
+    let slug = article_slug(response);
+    create_comment(&client, &slug, token.clone());
+    let url = format!("/api/articles/{}/comments", slug);
+
+    let response = client.get(url.clone()).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let other_token = login_as(&client, "commenter", "commenter@realworld.io");
+    let response = client
+        .get(url.clone())
+        .header(token_header(other_token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client
+        .post(url.clone())
+        .header(ContentType::JSON)
+        .header(token_header(other_token))
+        .body(json_string!({"comment": {"body": "Sneaky"}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client.get(url).header(token_header(token)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+/// Test paging and ordering of comment threads.
+fn test_comment_pagination() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let slug = article_slug(create_article(&client, token.clone()));
+
+    let first = create_comment(&client, &slug, token.clone());
+    let second = create_comment(&client, &slug, token.clone());
+    let third = create_comment(&client, &slug, token.clone());
+    let reply = reply_to_comment(&client, &slug, first, token);
+
+    let page = |query: &str| {
+        let response = client
+            .get(format!("/api/articles/{}/comments?{}", slug, query))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let value = response_json_value(response);
+        let ids: Vec<_> = value["comments"]
+            .as_array()
+            .expect("must have 'comments' field")
+            .iter()
+            .filter_map(|comment| comment["id"].as_i64())
+            .collect();
+        (ids, value["commentsCount"].as_i64())
+    };
+
+    assert_eq!(page("limit=2"), (vec![first, reply, second], Some(3)));
+    assert_eq!(page("limit=2&offset=2"), (vec![third], Some(3)));
+    assert_eq!(page("order=desc&limit=1"), (vec![third], Some(3)));
+    assert_eq!(
+        page("order=desc"),
+        (vec![third, second, first, reply], Some(3))
+    );
+
+    let response = client
+        .get("/api/articles/no-such-article/comments")
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+/// Test getting comments.
+fn test_get_comment() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let response = create_article(&client, token.clone());
+
+    let slug = article_slug(response);
+
+    let response = client
+        .get(format!("/api/articles/{}/comments", slug))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let value = response_json_value(response);
+    let comments_num = value
         .get("comments")
         .and_then(|comments| comments.as_array())
         .map(|comments| comments.len())
@@ -271,7 +1641,85 @@ fn article_slug(response: LocalResponse) -> String {
         .expect("Cannot extract article slug")
 }
 
+fn article_slugs(value: &serde_json::Value) -> Vec<String> {
+    articles_field(value, "slug")
+}
+
+fn article_dates(value: &serde_json::Value) -> Vec<String> {
+    articles_field(value, "createdAt")
+}
+
+fn articles_authors(value: &serde_json::Value) -> Vec<String> {
+    value
+        .get("articles")
+        .and_then(|articles| articles.as_array())
+        .expect("must have 'articles' field")
+        .iter()
+        .filter_map(|article| article.get("author"))
+        .filter_map(|author| author.get("username"))
+        .filter_map(|username| username.as_str())
+        .map(String::from)
+        .collect()
+}
+
+fn articles_tags(value: &serde_json::Value) -> Vec<Vec<String>> {
+    value
+        .get("articles")
+        .and_then(|articles| articles.as_array())
+        .expect("must have 'articles' field")
+        .iter()
+        .filter_map(|article| article.get("tagList"))
+        .filter_map(|tags| tags.as_array())
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| tag.as_str())
+                .map(String::from)
+                .collect()
+        })
+        .collect()
+}
+
+fn articles_counts(value: &serde_json::Value) -> Vec<i64> {
+    value
+        .get("articles")
+        .and_then(|articles| articles.as_array())
+        .expect("must have 'articles' field")
+        .iter()
+        .filter_map(|article| article.get("favoritesCount"))
+        .filter_map(|count| count.as_i64())
+        .collect()
+}
+
+fn articles_field(value: &serde_json::Value, field: &str) -> Vec<String> {
+    value
+        .get("articles")
+        .and_then(|articles| articles.as_array())
+        .expect("must have 'articles' field")
+        .iter()
+        .filter_map(|article| article.get(field))
+        .filter_map(|field| field.as_str())
+        .map(String::from)
+        .collect()
+}
+
 fn create_article(client: &Client, token: Token) -> LocalResponse {
+    create_article_with_tags(client, token, &["test", "foo", "bar"])
+}
+
+fn post_article<'c>(client: &'c Client, token: Token, body: String) -> LocalResponse<'c> {
+    client
+        .post("/api/articles")
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(body)
+        .dispatch()
+}
+
+fn create_article_with_tags<'c>(
+    client: &'c Client,
+    token: Token,
+    tags: &[&str],
+) -> LocalResponse<'c> {
     let response = client
         .post("/api/articles")
         .header(ContentType::JSON)
@@ -281,7 +1729,7 @@ fn create_article(client: &Client, token: Token) -> LocalResponse {
                     "title": ARTICLE_TITLE,
                     "description": "Well, it's a test article",
                     "body": ARTICLE_BODY,
-                    "tagList": ["test", "foo", "bar"]
+                    "tagList": tags
                 }
         }))
         .dispatch();
@@ -290,3 +1738,45 @@ fn create_article(client: &Client, token: Token) -> LocalResponse {
 
     response
 }
+
+fn reply_to_comment(client: &Client, slug: &str, parent: i64, token: Token) -> i64 {
+    let response = client
+        .post(format!("/api/articles/{}/comments", slug))
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"comment": {"body": "Reply", "parentId": parent}}))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+
+    response_json_value(response)["comment"]["id"]
+        .as_i64()
+        .expect("must have comment 'id' field")
+}
+
+fn get_comments(client: &Client, slug: &str) -> Vec<serde_json::Value> {
+    let response = client
+        .get(format!("/api/articles/{}/comments", slug))
+        .dispatch();
+    response_json_value(response)["comments"]
+        .as_array()
+        .expect("must have 'comments' field")
+        .clone()
+}
+
+fn create_comment(client: &Client, slug: &str, token: Token) -> i64 {
+    let response = client
+        .post(format!("/api/articles/{}/comments", slug))
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({"comment": {"body": "Like!"}}))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+
+    response_json_value(response)
+        .get("comment")
+        .and_then(|comment| comment.get("id"))
+        .and_then(|id| id.as_i64())
+        .expect("must have comment 'id' field")
+}
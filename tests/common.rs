@@ -7,11 +7,15 @@ use realworld;
 use rocket::http::{ContentType, Header, Status};
 use rocket::local::blocking::{Client, LocalResponse};
 use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
 use std::sync::Mutex;
 
 pub const USERNAME: &'static str = "smoketest";
 pub const EMAIL: &'static str = "smoketest@realworld.io";
 pub const PASSWORD: &'static str = "qweasdzxc";
+/// Access token lifetime of the test instance, in seconds.
+pub const ACCESS_TOKEN_LIFETIME: i64 = 30 * 60;
 
 /// Utility macro for turning `json!` into string.
 #[macro_export]
@@ -26,6 +30,10 @@ pub type Token = String;
 pub fn test_client() -> &'static Mutex<Client> {
     static INSTANCE: OnceCell<Mutex<Client>> = OnceCell::new();
     INSTANCE.get_or_init(|| {
+        std::env::set_var("MAIL_FILE", mail_file());
+        std::env::set_var("ACCESS_TOKEN_LIFETIME", ACCESS_TOKEN_LIFETIME.to_string());
+        // Counters make slugs predictable, random suffixes are tested in `database::slugs`.
+        std::env::set_var("SLUG_STRATEGY", "counter");
         let rocket = realworld::rocket();
         Mutex::from(Client::tracked(rocket).expect("valid rocket instance"))
     })
@@ -33,9 +41,14 @@ pub fn test_client() -> &'static Mutex<Client> {
 
 /// Retrieve a token registering a user if required.
 pub fn login(client: &Client) -> Token {
-    try_login(client).unwrap_or_else(|| {
-        register(client, USERNAME, EMAIL, PASSWORD);
-        try_login(client).expect("Cannot login")
+    login_as(client, USERNAME, EMAIL)
+}
+
+/// Retrieve a token for an arbitrary user registering it if required.
+pub fn login_as(client: &Client, username: &str, email: &str) -> Token {
+    try_login(client, email).unwrap_or_else(|| {
+        register(client, username, email, PASSWORD);
+        try_login(client, email).expect("Cannot login")
     })
 }
 
@@ -44,6 +57,21 @@ pub fn token_header(token: Token) -> Header<'static> {
     Header::new("authorization", format!("Token {}", token))
 }
 
+/// File where the test instance writes its mail.
+pub fn mail_file() -> PathBuf {
+    std::env::temp_dir().join("realworld-test-mail.jsonl")
+}
+
+/// Retrieve the last mail sent to the given address.
+pub fn last_mail_to(email: &str) -> Option<Value> {
+    fs::read_to_string(mail_file())
+        .ok()?
+        .lines()
+        .rev()
+        .map(|line| serde_json::from_str::<Value>(line).expect("can't parse mail"))
+        .find(|mail| mail.get("to").and_then(|to| to.as_str()) == Some(email))
+}
+
 /// Helper function for converting response to json value.
 pub fn response_json_value<'a>(response: LocalResponse<'a>) -> Value {
     let body = response.into_string().unwrap();
@@ -52,12 +80,12 @@ pub fn response_json_value<'a>(response: LocalResponse<'a>) -> Value {
 
 // Internal stuff
 
-/// Login as default user returning None if login is not found
-fn try_login(client: &Client) -> Option<Token> {
+/// Login as given user returning None if login is not found
+fn try_login(client: &Client, email: &str) -> Option<Token> {
     let response = client
         .post("/api/users/login")
         .header(ContentType::JSON)
-        .body(json_string!({"user": {"email": EMAIL, "password": PASSWORD}}))
+        .body(json_string!({"user": {"email": email, "password": PASSWORD}}))
         .dispatch();
 
     if response.status() == Status::UnprocessableEntity {
@@ -0,0 +1,145 @@
+//! Test that responses never expose the email of anybody but the caller.
+
+mod common;
+
+use common::*;
+use rocket::http::{ContentType, Method, Status};
+use rocket::local::blocking::Client;
+use serde_json::Value;
+
+const AUTHOR: &str = "privateauthor";
+const AUTHOR_EMAIL: &str = "privateauthor@realworld.io";
+const READER: &str = "privatereader";
+const READER_EMAIL: &str = "privatereader@realworld.io";
+
+#[test]
+/// Test public endpoints as an anonymous client and as another user.
+fn test_no_foreign_emails() {
+    let client = test_client().lock().unwrap();
+    let author_token = login_as(&client, AUTHOR, AUTHOR_EMAIL);
+    let slug = create_article(&client, author_token.clone());
+    let comment = format!("/api/articles/{}/comments", slug);
+    check(
+        &client,
+        Method::Post,
+        &comment,
+        Some((author_token, AUTHOR_EMAIL)),
+        Some(json_string!({"comment": {"body": "Author's note"}})),
+    );
+
+    let reader_token = login_as(&client, READER, READER_EMAIL);
+    let reader = Some((reader_token, READER_EMAIL));
+    let public = [
+        "/api/articles".to_string(),
+        format!("/api/articles?author={}", AUTHOR),
+        "/api/articles?tag=privacy".to_string(),
+        "/api/articles/search?q=privacy".to_string(),
+        format!("/api/articles/{}", slug),
+        format!("/api/articles/{}/revisions", slug),
+        comment.clone(),
+        format!("/api/profiles/{}", AUTHOR),
+        "/api/tags".to_string(),
+    ];
+    for url in &public {
+        check(&client, Method::Get, url, None, None);
+        check(&client, Method::Get, url, reader.clone(), None);
+    }
+
+    let mutations = [
+        (Method::Post, format!("/api/profiles/{}/follow", AUTHOR)),
+        (Method::Get, "/api/articles/feed".to_string()),
+        (Method::Delete, format!("/api/profiles/{}/follow", AUTHOR)),
+        (Method::Post, format!("/api/articles/{}/favorite", slug)),
+        (Method::Delete, format!("/api/articles/{}/favorite", slug)),
+        (Method::Get, "/api/user".to_string()),
+    ];
+    for (method, url) in &mutations {
+        check(&client, *method, url, reader.clone(), None);
+    }
+    check(
+        &client,
+        Method::Post,
+        &comment,
+        reader,
+        Some(json_string!({"comment": {"body": "Reader's reply"}})),
+    );
+}
+
+/// Create a published article of the author, returning its slug.
+fn create_article(client: &Client, token: Token) -> String {
+    let response = client
+        .post("/api/articles")
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({
+            "article": {
+                "title": "Privacy matters",
+                "description": "Who can see what",
+                "body": "Nobody but you should see your privacy settings.",
+                "tagList": ["privacy"]
+            }
+        }))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    response_json_value(response)["article"]["slug"]
+        .as_str()
+        .expect("must have 'slug' field")
+        .to_string()
+}
+
+/// Request `url` and assert that the only email in the response, if any, is
+/// the one of the caller.
+fn check(
+    client: &Client,
+    method: Method,
+    url: &str,
+    caller: Option<(Token, &str)>,
+    body: Option<String>,
+) {
+    let mut request = client.req(method, url);
+    if let Some(body) = body {
+        request = request.header(ContentType::JSON).body(body);
+    }
+    if let Some((token, _)) = caller.clone() {
+        request = request.header(token_header(token));
+    }
+    let response = request.dispatch();
+    assert_eq!(response.status(), Status::Ok, "{} {}", method, url);
+
+    let body = response.into_string().unwrap_or_default();
+    let value: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+    let mut found = vec![];
+    emails(&value, &mut found);
+    let own = caller.map(|(_, email)| email);
+    for email in found {
+        assert_eq!(
+            Some(email.as_str()),
+            own,
+            "{} {} leaks an email",
+            method,
+            url
+        );
+    }
+    for email in &[AUTHOR_EMAIL, READER_EMAIL] {
+        if Some(*email) != own {
+            assert!(!body.contains(email), "{} {} leaks {}", method, url, email);
+        }
+    }
+}
+
+/// Collect values of all `email` fields, however deep.
+fn emails(value: &Value, found: &mut Vec<String>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                if key == "email" {
+                    found.push(value.as_str().unwrap_or_default().to_string());
+                }
+                emails(value, found);
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| emails(value, found)),
+        _ => {}
+    }
+}
@@ -3,7 +3,7 @@
 mod common;
 
 use common::*;
-use rocket::http::Status;
+use rocket::http::{Method, Status};
 
 #[test]
 /// Test profile getting.
@@ -48,3 +48,47 @@ fn test_follow_and_unfollow() {
 
     assert_eq!(response.status(), Status::Ok);
 }
+
+#[test]
+/// Test that following and unfollowing twice is fine.
+fn test_follow_idempotent() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    register(
+        &client,
+        "twicefollowed",
+        "twicefollowed@realworld.io",
+        PASSWORD,
+    );
+
+    let requests = [
+        (Method::Post, true),
+        (Method::Post, true),
+        (Method::Delete, false),
+        (Method::Delete, false),
+    ];
+    for (method, following) in &requests {
+        let response = client
+            .req(*method, "/api/profiles/twicefollowed/follow")
+            .header(token_header(token.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let value = response_json_value(response);
+        assert_eq!(value["profile"]["following"].as_bool(), Some(*following));
+    }
+}
+
+#[test]
+/// Test following a user which doesn't exist.
+fn test_follow_missing_user() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+
+    let response = client
+        .post("/api/profiles/no-such-user/follow")
+        .header(token_header(token))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::NotFound);
+}
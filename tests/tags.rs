@@ -3,6 +3,8 @@
 mod common;
 
 use common::*;
+use rocket::http::{ContentType, Status};
+use rocket::local::blocking::{Client, LocalResponse};
 
 #[test]
 /// Test tags getting.
@@ -13,3 +15,124 @@ fn test_get_tags() {
     let value = response_json_value(response);
     value.get("tags").expect("must have 'tags' field");
 }
+
+#[test]
+/// Test that tags are normalized and counted.
+fn test_tags_normalized_and_counted() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let response = post_article(
+        &client,
+        token,
+        &["  Normalized ", "NORMALIZED", "", "Other"],
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let tag_list = value
+        .get("article")
+        .and_then(|article| article.get("tagList"))
+        .expect("must have 'tagList' field");
+    assert_eq!(tag_list, &serde_json::json!(["normalized", "other"]));
+
+    let value = response_json_value(client.get("/api/tags").dispatch());
+    let tags = value
+        .get("tags")
+        .and_then(|tags| tags.as_array())
+        .expect("must have 'tags' field");
+    assert!(tags.contains(&serde_json::json!("normalized")));
+
+    let counts: Vec<i64> = value
+        .get("tagCounts")
+        .and_then(|counts| counts.as_array())
+        .expect("must have 'tagCounts' field")
+        .iter()
+        .filter_map(|count| count.get("articlesCount"))
+        .filter_map(|count| count.as_i64())
+        .collect();
+    assert_eq!(counts.len(), tags.len());
+    assert!(counts.windows(2).all(|pair| pair[0] >= pair[1]));
+}
+
+#[test]
+/// Test that overly long tags are rejected.
+fn test_long_tag_rejected() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let tag = "x".repeat(33);
+    let response = post_article(&client, token, &[&tag]);
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let value = response_json_value(response);
+    value
+        .get("errors")
+        .and_then(|errors| errors.get("tagList"))
+        .expect("must have 'tagList' errors");
+}
+
+#[test]
+/// Test following tags and getting their articles in the feed.
+fn test_tag_following_feed() {
+    let client = test_client().lock().unwrap();
+    let follower = login_as(&client, "tagfollower", "tagfollower@realworld.io");
+    let response = client
+        .post("/api/tags/FeedTag/follow")
+        .header(token_header(follower.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    assert_eq!(
+        value.get("tag"),
+        Some(&serde_json::json!({"name": "feedtag", "following": true}))
+    );
+
+    let response = post_article(&client, login(&client), &["feedtag"]);
+    let slug = response_json_value(response)
+        .get("article")
+        .and_then(|article| article.get("slug"))
+        .and_then(|slug| slug.as_str())
+        .map(String::from)
+        .expect("must have article 'slug'");
+
+    let feed_slugs = |source: &str| {
+        let response = client
+            .get(format!("/api/articles/feed?source={}&limit=100", source))
+            .header(token_header(follower.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response_json_value(response)
+            .get("articles")
+            .and_then(|articles| articles.as_array())
+            .expect("must have 'articles' field")
+            .iter()
+            .filter_map(|article| article.get("slug"))
+            .filter_map(|slug| slug.as_str())
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    assert!(feed_slugs("tags").contains(&slug));
+    assert!(feed_slugs("all").contains(&slug));
+    assert!(!feed_slugs("authors").contains(&slug));
+
+    let response = client
+        .delete("/api/tags/feedtag/follow")
+        .header(token_header(follower.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(!feed_slugs("tags").contains(&slug));
+}
+
+fn post_article<'c>(client: &'c Client, token: Token, tags: &[&str]) -> LocalResponse<'c> {
+    client
+        .post("/api/articles")
+        .header(ContentType::JSON)
+        .header(token_header(token))
+        .body(json_string!({
+            "article": {
+                "title": "Tagged article",
+                "description": "Article with tags",
+                "body": "Tags!",
+                "tagList": tags
+            }
+        }))
+        .dispatch()
+}
@@ -4,7 +4,7 @@ mod common;
 
 use common::*;
 use rocket::http::{ContentType, Status};
-use rocket::local::blocking::LocalResponse;
+use rocket::local::blocking::{Client, LocalResponse};
 
 #[test]
 /// Register new user, handling repeated registration as well.
@@ -105,6 +105,27 @@ fn test_login() {
         .expect("token must be a string");
 }
 
+#[test]
+/// Check that access tokens expire after `ACCESS_TOKEN_LIFETIME`.
+fn test_access_token_lifetime() {
+    let client = test_client().lock().unwrap();
+    let token = login(&client);
+    let now = chrono::Utc::now().timestamp();
+
+    let payload = token.split('.').nth(1).expect("token must be a JWT");
+    let payload =
+        base64::decode_config(payload, base64::URL_SAFE_NO_PAD).expect("payload must be base64");
+    let claims: serde_json::Value = serde_json::from_slice(&payload).expect("payload must be json");
+    let exp = claims["exp"].as_i64().expect("must have 'exp' claim");
+    assert!(
+        (exp - now - ACCESS_TOKEN_LIFETIME).abs() <= 5,
+        "exp {} is not {}s after {}",
+        exp,
+        ACCESS_TOKEN_LIFETIME,
+        now
+    );
+}
+
 #[test]
 /// Check that `/user` endpoint returns expected data.
 fn test_get_user() {
@@ -133,6 +154,205 @@ fn test_put_user() {
     check_user_response(response);
 }
 
+#[test]
+/// Refresh tokens are single use and reusing one revokes its whole family.
+fn test_refresh_token_rotation() {
+    let client = test_client().lock().unwrap();
+    login(&client);
+    let first = login_refresh_token(&client);
+
+    let response = refresh(&client, &first);
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let user = value.get("user").expect("must have a 'user' field");
+    assert!(user.get("token").is_some());
+    let second = user
+        .get("refreshToken")
+        .and_then(|token| token.as_str())
+        .map(String::from)
+        .expect("must have a 'refreshToken' field");
+    assert_ne!(first, second);
+
+    let response = refresh(&client, &first);
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = refresh(&client, &second);
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[test]
+/// Logging out revokes the refresh token.
+fn test_logout() {
+    let client = test_client().lock().unwrap();
+    login(&client);
+    let token = login_refresh_token(&client);
+
+    let response = client
+        .post("/api/users/logout")
+        .header(ContentType::JSON)
+        .body(json_string!({"user": {"refreshToken": token}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = refresh(&client, &token);
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[test]
+/// Logging out everywhere makes previously issued tokens invalid.
+fn test_logout_everywhere() {
+    let client = test_client().lock().unwrap();
+    let token = login_as(&client, "revoker", "revoker@realworld.io");
+
+    let response = client
+        .get("/api/user")
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .post("/api/users/logout/all")
+        .header(token_header(token.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .get("/api/user")
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let token = login_as(&client, "revoker", "revoker@realworld.io");
+    let response = client
+        .get("/api/user")
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+/// Garbage tokens are rejected with 401.
+fn test_invalid_token() {
+    let client = test_client().lock().unwrap();
+    let response = client
+        .get("/api/user")
+        .header(token_header("garbage".to_string()))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[test]
+/// Changing password requires the current one and revokes old tokens,
+/// except for the new refresh token returned.
+fn test_change_password() {
+    let client = test_client().lock().unwrap();
+    let email = "changer@realworld.io";
+    let token = login_as(&client, "changer", email);
+    let new_password = "zxcasdqwe";
+
+    let response = client
+        .put("/api/user")
+        .header(token_header(token.clone()))
+        .header(ContentType::JSON)
+        .body(json_string!({"user": {"password": new_password, "currentPassword": "wrong"}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = client
+        .put("/api/user")
+        .header(token_header(token.clone()))
+        .header(ContentType::JSON)
+        .body(json_string!({"user": {"password": new_password, "currentPassword": PASSWORD}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let value = response_json_value(response);
+    let new_token = value["user"]["token"]
+        .as_str()
+        .map(String::from)
+        .expect("must have a token");
+    let refresh_token = value["user"]["refreshToken"]
+        .as_str()
+        .expect("must have a refresh token");
+    assert_eq!(refresh(&client, refresh_token).status(), Status::Ok);
+
+    let response = client
+        .get("/api/user")
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = login_with(&client, email, new_password);
+    assert_eq!(response.status(), Status::Ok);
+
+    // Restore password for subsequent runs.
+    let response = client
+        .put("/api/user")
+        .header(token_header(new_token))
+        .header(ContentType::JSON)
+        .body(json_string!({"user": {"password": PASSWORD, "currentPassword": new_password}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+/// Password reset token is mailed and can be used only once.
+fn test_password_reset() {
+    let client = test_client().lock().unwrap();
+    let email = "forgetful@realworld.io";
+    let token = login_as(&client, "forgetful", email);
+
+    let response = client
+        .post("/api/users/password-reset")
+        .header(ContentType::JSON)
+        .body(json_string!({"user": {"email": email}}))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let reset_token = last_mail_to(email)
+        .and_then(|mail| {
+            mail.get("body")
+                .and_then(|body| body.as_str())
+                .map(String::from)
+        })
+        .and_then(|body| body.lines().last().map(String::from))
+        .expect("must have been mailed a token");
+
+    let confirm = json_string!({"user": {"token": reset_token, "password": PASSWORD}});
+    let response = client
+        .post("/api/users/password-reset/confirm")
+        .header(ContentType::JSON)
+        .body(confirm.clone())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .post("/api/users/password-reset/confirm")
+        .header(ContentType::JSON)
+        .body(confirm)
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = client
+        .get("/api/user")
+        .header(token_header(token))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[test]
+/// Password reset doesn't reveal whether the email is registered.
+fn test_password_reset_unknown_email() {
+    let client = test_client().lock().unwrap();
+    let response = client
+        .post("/api/users/password-reset")
+        .header(ContentType::JSON)
+        .body(json_string!({"user": {"email": "nobody@realworld.io"}}))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+}
+
 // Utility functions
 
 /// Assert that body contains "user" response with expected fields.
@@ -147,6 +367,32 @@ fn check_user_response(response: LocalResponse) {
     assert!(user.get("token").is_some());
 }
 
+fn login_with<'c>(client: &'c Client, email: &str, password: &str) -> LocalResponse<'c> {
+    client
+        .post("/api/users/login")
+        .header(ContentType::JSON)
+        .body(json_string!({"user": {"email": email, "password": password}}))
+        .dispatch()
+}
+
+/// Login as default user returning a fresh refresh token.
+fn login_refresh_token(client: &Client) -> String {
+    response_json_value(login_with(client, EMAIL, PASSWORD))
+        .get("user")
+        .and_then(|user| user.get("refreshToken"))
+        .and_then(|token| token.as_str())
+        .map(String::from)
+        .expect("Cannot extract refresh token")
+}
+
+fn refresh<'c>(client: &'c Client, token: &str) -> LocalResponse<'c> {
+    client
+        .post("/api/users/refresh")
+        .header(ContentType::JSON)
+        .body(json_string!({"user": {"refreshToken": token}}))
+        .dispatch()
+}
+
 fn check_user_validation_errors(response: LocalResponse) {
     let value = response_json_value(response);
     let username_error = value